@@ -0,0 +1,417 @@
+//! Controller for simulating network partitions and resets on a pair of mock IO streams
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, PoisonError, Weak},
+};
+
+use crate::{
+    error::Inner,
+    flush::Unflushed,
+    primitives::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+    recording::Tap,
+    simulation::Simulation,
+    stats::StatsCell,
+};
+
+/// Direction of data flow relative to the mock IO stream a controller was obtained from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Data written by the stream
+    Outgoing,
+    /// Data read by the stream
+    Incoming,
+    /// Data flowing in both directions
+    Both,
+}
+
+impl Direction {
+    fn includes_outgoing(self) -> bool {
+        matches!(self, Self::Outgoing | Self::Both)
+    }
+
+    fn includes_incoming(self) -> bool {
+        matches!(self, Self::Incoming | Self::Both)
+    }
+}
+
+/// Controller for a connection between a pair of mock IO streams
+///
+/// A controller can be used to pause delivery of data, silently drop data (blackhole), resume delivery and reset the
+/// connection while the streams are being used on other threads or tasks. Controller does not keep the connection
+/// alive, i.e., all the operations become no-op once the streams are dropped.
+///
+/// ```rust
+/// # use std::io::{ErrorKind, Read, Write};
+/// use mock_io::sync::{Direction, MockStream};
+///
+/// let (mut client, mut server) = MockStream::pair();
+/// let controller = client.controller();
+///
+/// controller.pause(Direction::Outgoing);
+/// client.write_all(b"hello").unwrap();
+/// controller.resume(Direction::Outgoing);
+///
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"hello", &buf);
+///
+/// controller.reset();
+/// assert_eq!(ErrorKind::ConnectionReset, server.read(&mut buf).unwrap_err().kind());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Controller {
+    outgoing: Weak<dyn Control>,
+    incoming: Weak<dyn Control>,
+    reset: Arc<AtomicBool>,
+    unflushed: Unflushed,
+}
+
+impl Controller {
+    /// Stops delivering data in given direction. Data written while paused is held until it is resumed or discarded.
+    pub fn pause(&self, direction: Direction) {
+        self.for_each(direction, |control| control.set_mode(Mode::Paused));
+    }
+
+    /// Silently drops all the data written in given direction (writes still succeed)
+    pub fn blackhole(&self, direction: Direction) {
+        self.for_each(direction, |control| control.set_mode(Mode::Blackhole));
+    }
+
+    /// Resumes delivering data in given direction. Data held while paused is delivered before any new data.
+    pub fn resume(&self, direction: Direction) {
+        self.for_each(direction, |control| control.set_mode(Mode::Open));
+    }
+
+    /// Drops the data held in given direction while it was paused
+    pub fn discard(&self, direction: Direction) {
+        self.for_each(direction, |control| control.discard());
+    }
+
+    /// Resets the connection. All subsequent reads and writes on both streams fail with `ConnectionReset` error and
+    /// any undelivered data is lost.
+    pub fn reset(&self) {
+        self.reset.store(true, Ordering::SeqCst);
+
+        // The stream of this controller resets its outgoing wire (only the first reset of a connection is recorded)
+        if let Some(control) = self.outgoing.upgrade() {
+            control.reset(true);
+        }
+
+        if let Some(control) = self.incoming.upgrade() {
+            control.reset(false);
+        }
+    }
+
+    /// Returns `true` if the connection has been reset
+    pub fn is_reset(&self) -> bool {
+        self.reset.load(Ordering::SeqCst)
+    }
+
+    /// Panics if either stream of the connection was dropped while holding unflushed data in `FlushMode::Strict`.
+    /// Unlike the drop itself, this can be checked on the test thread even if the streams are used on other threads.
+    pub fn assert_flushed(&self) {
+        self.unflushed.assert_empty();
+    }
+
+    /// Returns the log of unflushed data dropped by the streams of the connection
+    pub(crate) fn unflushed(&self) -> Unflushed {
+        self.unflushed.clone()
+    }
+
+    /// Routes the deliveries in both directions of the connection through given simulation
+    pub(crate) fn simulate(&self, simulation: &Simulation) {
+        self.for_each(Direction::Both, |control| control.simulate(simulation));
+    }
+
+    fn for_each(&self, direction: Direction, f: impl Fn(&dyn Control)) {
+        if direction.includes_outgoing() {
+            if let Some(control) = self.outgoing.upgrade() {
+                f(control.as_ref());
+            }
+        }
+
+        if direction.includes_incoming() {
+            if let Some(control) = self.incoming.upgrade() {
+                f(control.as_ref());
+            }
+        }
+    }
+}
+
+/// Creates controllers for a pair of mock streams connected with `wire_1` (first to second) and `wire_2` (second to
+/// first) along with the reset flag shared by the connection
+pub(crate) fn pair<S: Deliver + 'static>(
+    wire_1: &Arc<Wire<S>>,
+    wire_2: &Arc<Wire<S>>,
+) -> (Controller, Controller, Arc<AtomicBool>) {
+    let reset = Arc::new(AtomicBool::new(false));
+
+    let wire_1: Arc<dyn Control> = wire_1.clone();
+    let wire_2: Arc<dyn Control> = wire_2.clone();
+
+    let controller_1 = Controller {
+        outgoing: Arc::downgrade(&wire_1),
+        incoming: Arc::downgrade(&wire_2),
+        reset: reset.clone(),
+        unflushed: Default::default(),
+    };
+
+    let controller_2 = Controller {
+        outgoing: Arc::downgrade(&wire_2),
+        incoming: Arc::downgrade(&wire_1),
+        reset: reset.clone(),
+        unflushed: controller_1.unflushed(),
+    };
+
+    (controller_1, controller_2, reset)
+}
+
+/// Returns an error if the connection has been reset
+pub(crate) fn check_reset(reset: &AtomicBool) -> Result<(), Inner> {
+    if reset.load(Ordering::SeqCst) {
+        Err(Inner::ConnectionReset)
+    } else {
+        Ok(())
+    }
+}
+
+/// Message sent over the channel between a pair of mock streams
+#[derive(Debug)]
+pub(crate) enum Message<P> {
+    Data(P),
+    Reset,
+}
+
+/// Channel sender used by a wire to deliver messages to the peer
+pub(crate) trait Deliver: Clone + fmt::Debug + Send + Sync + 'static {
+    type Packet: AsRef<[u8]> + fmt::Debug + Send + 'static;
+
+    fn deliver(&self, message: Message<Self::Packet>) -> Result<(), Inner>;
+
+    /// Closes the channel after all the messages delivered before (by dropping the sender unless overridden)
+    #[cfg(any(feature = "async-futures", feature = "async-tokio"))]
+    fn close(self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Open,
+    Paused,
+    Blackhole,
+}
+
+trait Control: fmt::Debug + Send + Sync {
+    fn set_mode(&self, mode: Mode);
+
+    fn discard(&self);
+
+    /// Resets the wire (`by_writer` is `true` if the stream writing to the wire resets the connection)
+    fn reset(&self, by_writer: bool);
+
+    fn simulate(&self, simulation: &Simulation);
+}
+
+/// One direction of the connection between a pair of mock streams
+#[derive(Debug)]
+pub(crate) struct Wire<S: Deliver> {
+    tap: Tap,
+    /// Statistics of the stream writing to the wire (bytes are pending once they are delivered or held)
+    stats: StatsCell,
+    state: Mutex<WireState<S>>,
+}
+
+#[derive(Debug)]
+struct WireState<S: Deliver> {
+    /// Sender of the channel to the peer (`None` once the channel is closed)
+    sender: Option<S>,
+    /// Whether the wire was closed (the channel is closed once the held packets are delivered)
+    closed: bool,
+    mode: Mode,
+    held: VecDeque<S::Packet>,
+    simulation: Option<(Simulation, u64)>,
+}
+
+impl<S: Deliver> Wire<S> {
+    pub(crate) fn new(sender: S, tap: Tap, stats: StatsCell) -> Self {
+        Self {
+            tap,
+            stats,
+            state: Mutex::new(WireState {
+                sender: Some(sender),
+                closed: false,
+                mode: Mode::Open,
+                held: Default::default(),
+                simulation: None,
+            }),
+        }
+    }
+
+    /// Closes the channel to the peer after all the data sent before is delivered (later sends fail)
+    #[cfg(any(feature = "async-futures", feature = "async-tokio"))]
+    pub(crate) fn close(&self) {
+        let mut state = self.lock();
+
+        if state.closed {
+            return;
+        }
+
+        state.closed = true;
+        self.tap.shutdown();
+
+        if state.mode != Mode::Paused {
+            state.close_channel();
+        }
+    }
+
+    /// Sends a packet to the peer according to the current mode of the wire
+    pub(crate) fn send(&self, packet: S::Packet) -> Result<(), Inner> {
+        let mut state = self.lock();
+
+        if state.closed {
+            return Err(Inner::NotConnected);
+        }
+
+        let len = packet.as_ref().len();
+
+        // Only data on its way to the peer is recorded (blackholed data never reaches the wire)
+        match state.mode {
+            Mode::Open => {
+                // Recorded before delivering, so that the peer cannot record its reply before this data
+                self.tap.data(packet.as_ref());
+
+                if let Err(error) = self.deliver(&state, packet) {
+                    self.tap.retract();
+                    return Err(error);
+                }
+
+                self.stats.enqueue(len);
+                Ok(())
+            }
+            Mode::Paused => {
+                self.tap.data(packet.as_ref());
+                state.held.push_back(packet);
+                self.stats.enqueue(len);
+                Ok(())
+            }
+            Mode::Blackhole => Ok(()),
+        }
+    }
+
+    /// Resets the connection after all the data sent before is delivered to the peer (without setting the reset flag
+    /// right away)
+    #[cfg(feature = "sync")]
+    pub(crate) fn abort(&self) -> Result<(), Inner> {
+        self.tap.reset(true);
+        self.lock().sender()?.deliver(Message::Reset)
+    }
+
+    /// Delivers a packet to the peer right away or queues it in the simulation the wire is attached to
+    fn deliver(&self, state: &WireState<S>, packet: S::Packet) -> Result<(), Inner> {
+        let sender = state.sender()?;
+
+        match &state.simulation {
+            Some((simulation, source)) => {
+                let sender = sender.clone();
+                simulation.schedule(*source, move || {
+                    let _ = sender.deliver(Message::Data(packet));
+                });
+                Ok(())
+            }
+            None => sender.deliver(Message::Data(packet)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WireState<S>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<S: Deliver> WireState<S> {
+    fn sender(&self) -> Result<&S, Inner> {
+        self.sender.as_ref().ok_or(Inner::NotConnected)
+    }
+
+    /// Closes the channel to the peer after all the packets queued in the simulation are delivered
+    #[cfg(any(feature = "async-futures", feature = "async-tokio"))]
+    fn close_channel(&mut self) {
+        let sender = match self.sender.take() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        match &self.simulation {
+            Some((simulation, source)) => simulation.schedule(*source, move || sender.close()),
+            None => sender.close(),
+        }
+    }
+}
+
+impl<S: Deliver> Control for Wire<S> {
+    fn set_mode(&self, mode: Mode) {
+        let mut state = self.lock();
+        state.mode = mode;
+
+        if mode == Mode::Open {
+            while let Some(packet) = state.held.pop_front() {
+                let len = packet.as_ref().len();
+
+                if self.deliver(&state, packet).is_err() {
+                    self.stats.discard(len);
+                }
+            }
+
+            #[cfg(any(feature = "async-futures", feature = "async-tokio"))]
+            if state.closed {
+                state.close_channel();
+            }
+        }
+    }
+
+    fn discard(&self) {
+        let mut state = self.lock();
+        let len = state
+            .held
+            .drain(..)
+            .map(|packet| packet.as_ref().len())
+            .sum();
+        self.stats.discard(len);
+    }
+
+    fn reset(&self, by_writer: bool) {
+        let mut state = self.lock();
+        let len = state
+            .held
+            .drain(..)
+            .map(|packet| packet.as_ref().len())
+            .sum();
+        self.stats.discard(len);
+        self.tap.reset(by_writer);
+
+        if let Ok(sender) = state.sender() {
+            let _ = sender.deliver(Message::Reset);
+        }
+    }
+
+    fn simulate(&self, simulation: &Simulation) {
+        self.lock().simulation = Some((simulation.clone(), simulation.source()));
+    }
+}
+
+impl<S: Deliver> Drop for Wire<S> {
+    fn drop(&mut self) {
+        // Shutdown is recorded when the wire is closed, so it is only recorded here if the wire was never closed
+        let closed = self
+            .state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed;
+
+        if !closed {
+            self.tap.shutdown();
+        }
+    }
+}
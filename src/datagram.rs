@@ -0,0 +1,343 @@
+//! Building blocks shared by mock datagram sockets
+use std::{
+    collections::HashMap,
+    fmt, iter,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, PoisonError},
+};
+
+use crate::{
+    error::Inner,
+    primitives::{Mutex, MutexGuard},
+    rng::Rng,
+    socket_options::{next_port, EPHEMERAL_PORT_START},
+};
+
+/// Faults injected into the datagrams sent by a mock datagram socket
+///
+/// Every probability is in range `0.0..=1.0`. By default, no faults are injected.
+///
+/// ```rust
+/// use mock_io::sync::DatagramFaults;
+///
+/// let faults = DatagramFaults::new().loss(0.1).duplication(0.05).reordering(0.2).seed(42);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatagramFaults {
+    loss: f64,
+    duplication: f64,
+    reordering: f64,
+    seed: u64,
+}
+
+impl DatagramFaults {
+    /// Creates a new fault configuration which does not inject any fault
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the probability of a datagram being lost
+    pub fn loss(mut self, probability: f64) -> Self {
+        self.loss = probability;
+        self
+    }
+
+    /// Sets the probability of a datagram being delivered twice
+    pub fn duplication(mut self, probability: f64) -> Self {
+        self.duplication = probability;
+        self
+    }
+
+    /// Sets the probability of a datagram being delivered after the next datagram (or when its destination next
+    /// receives, if no other datagram is sent in the meantime)
+    pub fn reordering(mut self, probability: f64) -> Self {
+        self.reordering = probability;
+        self
+    }
+
+    /// Sets the seed of the random number generator used for injecting faults
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Datagram sent between mock datagram sockets
+#[derive(Debug, Clone)]
+pub(crate) struct Datagram {
+    pub(crate) from: SocketAddr,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Datagram {
+    /// Copies the datagram into given buffer, truncating it if the buffer is too small
+    pub(crate) fn copy_to(&self, buf: &mut [u8]) -> usize {
+        copy_msg(&self.bytes, buf).0
+    }
+}
+
+/// Copies a message into given buffer and returns the number of bytes copied along with a flag which is `true` if
+/// the message was truncated because the buffer is too small
+pub(crate) fn copy_msg(message: &[u8], buf: &mut [u8]) -> (usize, bool) {
+    let len = buf.len().min(message.len());
+    buf[..len].copy_from_slice(&message[..len]);
+    (len, len < message.len())
+}
+
+/// State of faults injected into datagrams sent by a socket
+#[derive(Debug)]
+pub(crate) struct FaultState {
+    faults: DatagramFaults,
+    rng: Rng,
+    held: Option<(SocketAddr, Datagram)>,
+}
+
+impl FaultState {
+    pub(crate) fn new(faults: DatagramFaults) -> Self {
+        Self {
+            rng: Rng::new(faults.seed),
+            faults,
+            held: None,
+        }
+    }
+
+    /// Returns the datagrams (along with their destinations) to be delivered after sending a datagram to `to`
+    pub(crate) fn apply(
+        &mut self,
+        to: SocketAddr,
+        datagram: Datagram,
+    ) -> Vec<(SocketAddr, Datagram)> {
+        let mut deliveries = Vec::new();
+
+        if self.rng.chance(self.faults.loss) {
+            return deliveries;
+        }
+
+        if self.held.is_none() && self.rng.chance(self.faults.reordering) {
+            self.held = Some((to, datagram));
+            return deliveries;
+        }
+
+        if self.rng.chance(self.faults.duplication) {
+            deliveries.push((to, datagram.clone()));
+        }
+
+        deliveries.push((to, datagram));
+        deliveries.extend(self.held.take());
+        deliveries
+    }
+
+    /// Takes the held datagram (along with its destination) if its destination matches given predicate
+    fn release(&mut self, matches: impl Fn(&SocketAddr) -> bool) -> Option<(SocketAddr, Datagram)> {
+        match &self.held {
+            Some((to, _)) if matches(to) => self.held.take(),
+            _ => None,
+        }
+    }
+}
+
+/// Address space of mock datagram sockets
+#[derive(Debug)]
+pub(crate) struct Registry<S> {
+    sockets: Mutex<HashMap<SocketAddr, Entry<S>>>,
+}
+
+/// Socket bound in a registry
+#[derive(Debug)]
+struct Entry<S> {
+    sender: S,
+    faults: Arc<Mutex<FaultState>>,
+}
+
+impl<S: DatagramSender> Registry<S> {
+    /// Binds a socket to given address. A free port is allocated if the port of given address is `0` (from the same
+    /// ephemeral ports as the addresses of mock streams created without explicit addresses).
+    fn bind(
+        &self,
+        mut addr: SocketAddr,
+        sender: S,
+        faults: Arc<Mutex<FaultState>>,
+    ) -> Result<SocketAddr, Inner> {
+        let mut sockets = self.lock();
+
+        if addr.port() == 0 {
+            let port = iter::repeat_with(next_port)
+                .take(usize::from(u16::MAX - EPHEMERAL_PORT_START) + 1)
+                .find(|&port| !sockets.contains_key(&SocketAddr::new(addr.ip(), port)))
+                .ok_or(Inner::AddrInUse)?;
+            addr.set_port(port);
+        }
+
+        if sockets.contains_key(&addr) {
+            return Err(Inner::AddrInUse);
+        }
+
+        sockets.insert(addr, Entry { sender, faults });
+        Ok(addr)
+    }
+
+    fn unbind(&self, addr: &SocketAddr) {
+        self.lock().remove(addr);
+    }
+
+    /// Returns the sender of the socket bound to given address, falling back to the socket bound to the unspecified
+    /// address on the same port (if any)
+    fn get(&self, addr: &SocketAddr) -> Option<S> {
+        let sockets = self.lock();
+        resolve(&sockets, addr).map(|bound| sockets[&bound].sender.clone())
+    }
+
+    /// Delivers given datagrams to their destinations (datagrams sent to an address without any socket are dropped)
+    fn deliver(&self, deliveries: impl IntoIterator<Item = (SocketAddr, Datagram)>) {
+        for (to, datagram) in deliveries {
+            if let Some(sender) = self.get(&to) {
+                sender.send_datagram(datagram);
+            }
+        }
+    }
+
+    /// Delivers the datagrams held for reordering by any socket which are sent to the socket bound to given address
+    fn release(&self, addr: SocketAddr) {
+        let sockets = self.lock();
+
+        let released: Vec<_> = sockets
+            .values()
+            .filter_map(|entry| {
+                lock(&entry.faults).release(|to| resolve(&sockets, to) == Some(addr))
+            })
+            .collect();
+
+        drop(sockets);
+        self.deliver(released);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<SocketAddr, Entry<S>>> {
+        lock(&self.sockets)
+    }
+}
+
+/// Returns the address of the socket which receives datagrams sent to given address (the socket bound to the address
+/// itself or the socket bound to the unspecified address on the same port)
+fn resolve<S>(sockets: &HashMap<SocketAddr, Entry<S>>, addr: &SocketAddr) -> Option<SocketAddr> {
+    if sockets.contains_key(addr) {
+        return Some(*addr);
+    }
+
+    let unspecified = match addr.ip() {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let addr = SocketAddr::new(unspecified, addr.port());
+
+    sockets.contains_key(&addr).then_some(addr)
+}
+
+impl<S> Default for Registry<S> {
+    fn default() -> Self {
+        Self {
+            sockets: Default::default(),
+        }
+    }
+}
+
+/// Channel sender used to deliver datagrams to a mock datagram socket
+pub(crate) trait DatagramSender: Clone + fmt::Debug + Send + Sync {
+    /// Delivers a datagram to the socket (datagrams sent to closed sockets are silently dropped)
+    fn send_datagram(&self, datagram: Datagram);
+}
+
+/// Backend independent part of a mock datagram socket
+#[derive(Debug)]
+pub(crate) struct SocketCore<S: DatagramSender> {
+    registry: Arc<Registry<S>>,
+    local_addr: SocketAddr,
+    peer_addr: Mutex<Option<SocketAddr>>,
+    faults: Arc<Mutex<FaultState>>,
+}
+
+impl<S: DatagramSender> SocketCore<S> {
+    pub(crate) fn bind(
+        registry: &Arc<Registry<S>>,
+        addr: SocketAddr,
+        sender: S,
+    ) -> Result<Self, Inner> {
+        let faults = Arc::new(Mutex::new(FaultState::new(Default::default())));
+        let local_addr = registry.bind(addr, sender, faults.clone())?;
+
+        Ok(Self {
+            registry: registry.clone(),
+            local_addr,
+            peer_addr: Default::default(),
+            faults,
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub(crate) fn peer_addr(&self) -> Result<SocketAddr, Inner> {
+        lock(&self.peer_addr).ok_or(Inner::NotConnected)
+    }
+
+    pub(crate) fn connect(&self, addr: SocketAddr) {
+        *lock(&self.peer_addr) = Some(addr);
+    }
+
+    pub(crate) fn set_faults(&self, faults: DatagramFaults) {
+        let previous = std::mem::replace(&mut *lock(&self.faults), FaultState::new(faults));
+        self.registry.deliver(previous.held);
+    }
+
+    /// Sends a datagram to given address. A socket bound to the unspecified address sends from the address it sends
+    /// to (as all the sockets of a mock network are local), so that peers connected to that address accept replies.
+    pub(crate) fn send_to(&self, buf: &[u8], to: SocketAddr) -> usize {
+        let from = if self.local_addr.ip().is_unspecified() {
+            SocketAddr::new(to.ip(), self.local_addr.port())
+        } else {
+            self.local_addr
+        };
+        let datagram = Datagram {
+            from,
+            bytes: buf.to_vec(),
+        };
+
+        let deliveries = lock(&self.faults).apply(to, datagram);
+        self.registry.deliver(deliveries);
+
+        buf.len()
+    }
+
+    pub(crate) fn send(&self, buf: &[u8]) -> Result<usize, Inner> {
+        let peer_addr = self.peer_addr()?;
+        Ok(self.send_to(buf, peer_addr))
+    }
+
+    /// Delivers the datagrams held for reordering which are sent to this socket (called before every receive, so that
+    /// a held datagram is not lost if no other datagram is sent after it)
+    pub(crate) fn release_held(&self) {
+        self.registry.release(self.local_addr);
+    }
+
+    /// Returns `false` if the socket is connected and the datagram is not sent by the connected peer
+    pub(crate) fn accepts(&self, datagram: &Datagram) -> bool {
+        match *lock(&self.peer_addr) {
+            Some(peer_addr) => peer_addr == datagram.from,
+            None => true,
+        }
+    }
+}
+
+impl<S: DatagramSender> Drop for SocketCore<S> {
+    fn drop(&mut self) {
+        self.registry.unbind(&self.local_addr);
+
+        // Datagram held for reordering is delivered as the socket will not send any other datagram
+        let held = lock(&self.faults).held.take();
+        self.registry.deliver(held);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
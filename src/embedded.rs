@@ -0,0 +1,2 @@
+//! Mock IO stream in `no_std` context (using `embedded_io::{Read, Write}` and their async counterparts)
+pub use crate::mock_stream::embedded::*;
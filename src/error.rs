@@ -31,7 +31,22 @@ impl IError for Error {
 #[doc(hidden)]
 impl From<Error> for std::io::Error {
     fn from(error: Error) -> Self {
-        std::io::Error::new(std::io::ErrorKind::Other, error)
+        let error = match error.inner {
+            Inner::Io(error) => return error,
+            inner => Error { inner },
+        };
+
+        let kind = match error.kind() {
+            ErrorKind::WouldBlock => std::io::ErrorKind::WouldBlock,
+            ErrorKind::TimedOut => std::io::ErrorKind::TimedOut,
+            ErrorKind::InvalidInput => std::io::ErrorKind::InvalidInput,
+            ErrorKind::ConnectionReset => std::io::ErrorKind::ConnectionReset,
+            ErrorKind::AddrInUse => std::io::ErrorKind::AddrInUse,
+            ErrorKind::NotConnected => std::io::ErrorKind::NotConnected,
+            _ => std::io::ErrorKind::Other,
+        };
+
+        std::io::Error::new(kind, error)
     }
 }
 
@@ -45,7 +60,7 @@ impl<T: Into<Inner>> From<T> for Error {
 }
 
 /// Different kinds of possible errors returned by functions in this crate
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum ErrorKind {
     /// Stream connecting error
@@ -54,6 +69,24 @@ pub enum ErrorKind {
     ChannelRecvError,
     /// Channel sending error
     ChannelSendError,
+    /// Operation would block
+    WouldBlock,
+    /// Operation timed out
+    TimedOut,
+    /// Invalid input parameter
+    InvalidInput,
+    /// Connection reset by peer
+    ConnectionReset,
+    /// Address already in use
+    AddrInUse,
+    /// Socket is not connected
+    NotConnected,
+    /// Requests expected by a mock server never arrived
+    MissingRequests,
+    /// TLS handshake failed
+    Tls,
+    /// Error returned by a real IO resource
+    Io,
     /// Other error
     Other,
 }
@@ -64,6 +97,15 @@ impl fmt::Display for ErrorKind {
             Self::StreamConnectError => write!(f, "Stream connecting error"),
             Self::ChannelRecvError => write!(f, "Channel receiving error"),
             Self::ChannelSendError => write!(f, "Channel sending error"),
+            Self::WouldBlock => write!(f, "Operation would block"),
+            Self::TimedOut => write!(f, "Operation timed out"),
+            Self::InvalidInput => write!(f, "Invalid input parameter"),
+            Self::ConnectionReset => write!(f, "Connection reset by peer"),
+            Self::AddrInUse => write!(f, "Address already in use"),
+            Self::NotConnected => write!(f, "Socket is not connected"),
+            Self::MissingRequests => write!(f, "Expected requests never arrived"),
+            Self::Tls => write!(f, "TLS handshake failed"),
+            Self::Io => write!(f, "IO error"),
             Self::Other => write!(f, "Other error"),
         }
     }
@@ -79,26 +121,48 @@ pub enum Inner {
     #[error("Sync channel receiving error: {0}")]
     SyncRecvError(#[from] std::sync::mpsc::RecvError),
     #[cfg(feature = "sync")]
-    #[error("Sync channel sending error: {0}")]
-    SyncSendError(#[from] std::sync::mpsc::SendError<Vec<u8>>),
+    #[error("Sync channel sending error")]
+    SyncSendError,
     #[cfg(feature = "async-futures")]
     #[error("Async stream connecting error")]
-    AsyncConnectError(#[from] async_channel::SendError<crate::futures::MockStream>),
+    AsyncConnectError,
     #[cfg(feature = "async-futures")]
     #[error("Async channel receiving error: {0}")]
     AsyncRecvError(#[from] async_channel::RecvError),
     #[cfg(feature = "async-futures")]
-    #[error("Async channel sending error: {0}")]
-    AsyncSendError(#[from] async_channel::SendError<Vec<u8>>),
+    #[error("Async channel sending error")]
+    AsyncSendError,
     #[cfg(feature = "async-tokio")]
     #[error("Tokio stream connecting error")]
-    TokioConnectError(#[from] tokio::sync::mpsc::error::SendError<crate::tokio::MockStream>),
+    TokioConnectError,
     #[cfg(feature = "async-tokio")]
     #[error("Tokio channel receiving error")]
     TokioRecvError,
     #[cfg(feature = "async-tokio")]
-    #[error("Tokio channel sending error: {0}")]
-    TokioSendError(#[from] tokio::sync::mpsc::error::SendError<Vec<u8>>),
+    #[error("Tokio channel sending error")]
+    TokioSendError,
+    #[error("Operation would block")]
+    WouldBlock,
+    #[error("Operation timed out")]
+    TimedOut,
+    #[error("Cannot set a 0 duration timeout")]
+    ZeroTimeout,
+    #[cfg(feature = "async-futures")]
+    #[error("Links are not supported by async-futures mock streams")]
+    UnsupportedLinks,
+    #[error("Connection reset by peer")]
+    ConnectionReset,
+    #[error("Address already in use")]
+    AddrInUse,
+    #[error("Socket is not connected")]
+    NotConnected,
+    #[error("Expected requests never arrived: {0}")]
+    MissingRequests(String),
+    #[cfg(feature = "rustls")]
+    #[error("TLS handshake failed: {0}")]
+    Tls(#[from] rustls::Error),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
     #[error("Other error")]
     Other,
 }
@@ -111,19 +175,31 @@ impl<'a> From<&'a Inner> for ErrorKind {
             #[cfg(feature = "sync")]
             Inner::SyncRecvError(_) => ErrorKind::ChannelRecvError,
             #[cfg(feature = "sync")]
-            Inner::SyncSendError(_) => ErrorKind::ChannelSendError,
+            Inner::SyncSendError => ErrorKind::ChannelSendError,
             #[cfg(feature = "async-futures")]
-            Inner::AsyncConnectError(_) => ErrorKind::StreamConnectError,
+            Inner::AsyncConnectError => ErrorKind::StreamConnectError,
             #[cfg(feature = "async-futures")]
             Inner::AsyncRecvError(_) => ErrorKind::ChannelRecvError,
             #[cfg(feature = "async-futures")]
-            Inner::AsyncSendError(_) => ErrorKind::ChannelSendError,
+            Inner::AsyncSendError => ErrorKind::ChannelSendError,
             #[cfg(feature = "async-tokio")]
-            Inner::TokioConnectError(_) => ErrorKind::StreamConnectError,
+            Inner::TokioConnectError => ErrorKind::StreamConnectError,
             #[cfg(feature = "async-tokio")]
             Inner::TokioRecvError => ErrorKind::ChannelRecvError,
             #[cfg(feature = "async-tokio")]
-            Inner::TokioSendError(_) => ErrorKind::ChannelSendError,
+            Inner::TokioSendError => ErrorKind::ChannelSendError,
+            Inner::WouldBlock => ErrorKind::WouldBlock,
+            Inner::TimedOut => ErrorKind::TimedOut,
+            Inner::ZeroTimeout => ErrorKind::InvalidInput,
+            #[cfg(feature = "async-futures")]
+            Inner::UnsupportedLinks => ErrorKind::InvalidInput,
+            Inner::ConnectionReset => ErrorKind::ConnectionReset,
+            Inner::AddrInUse => ErrorKind::AddrInUse,
+            Inner::NotConnected => ErrorKind::NotConnected,
+            Inner::MissingRequests(_) => ErrorKind::MissingRequests,
+            #[cfg(feature = "rustls")]
+            Inner::Tls(_) => ErrorKind::Tls,
+            Inner::Io(_) => ErrorKind::Io,
             Inner::Other => ErrorKind::Other,
         }
     }
@@ -135,3 +211,55 @@ impl From<std::sync::mpsc::SendError<crate::sync::MockStream>> for Inner {
         Self::SyncConnectError
     }
 }
+
+#[cfg(feature = "async-futures")]
+impl From<async_channel::SendError<crate::futures::MockStream>> for Inner {
+    fn from(_: async_channel::SendError<crate::futures::MockStream>) -> Self {
+        Self::AsyncConnectError
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl From<tokio::sync::mpsc::error::SendError<crate::tokio::MockStream>> for Inner {
+    fn from(_: tokio::sync::mpsc::error::SendError<crate::tokio::MockStream>) -> Self {
+        Self::TokioConnectError
+    }
+}
+
+#[cfg(feature = "sync")]
+impl From<std::sync::mpsc::TryRecvError> for Inner {
+    fn from(error: std::sync::mpsc::TryRecvError) -> Self {
+        match error {
+            std::sync::mpsc::TryRecvError::Empty => Self::WouldBlock,
+            std::sync::mpsc::TryRecvError::Disconnected => {
+                Self::SyncRecvError(std::sync::mpsc::RecvError)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl From<std::sync::mpsc::RecvTimeoutError> for Inner {
+    fn from(error: std::sync::mpsc::RecvTimeoutError) -> Self {
+        match error {
+            // Same as `std::net::TcpStream`, which reports `EAGAIN` on unix and `WSAETIMEDOUT` on windows
+            #[cfg(windows)]
+            std::sync::mpsc::RecvTimeoutError::Timeout => Self::TimedOut,
+            #[cfg(not(windows))]
+            std::sync::mpsc::RecvTimeoutError::Timeout => Self::WouldBlock,
+            std::sync::mpsc::RecvTimeoutError::Disconnected => {
+                Self::SyncRecvError(std::sync::mpsc::RecvError)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async-tokio")]
+impl From<tokio::sync::mpsc::error::TryRecvError> for Inner {
+    fn from(error: tokio::sync::mpsc::error::TryRecvError) -> Self {
+        match error {
+            tokio::sync::mpsc::error::TryRecvError::Empty => Self::WouldBlock,
+            tokio::sync::mpsc::error::TryRecvError::Disconnected => Self::TokioRecvError,
+        }
+    }
+}
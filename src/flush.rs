@@ -0,0 +1,148 @@
+//! Flush-aware buffering of data written to mock IO streams
+use std::{
+    mem,
+    sync::{Arc, PoisonError},
+};
+
+use crate::primitives::{Mutex, MutexGuard};
+
+/// Controls when data written to a mock IO stream is delivered to the peer
+///
+/// ```rust
+/// # use std::io::{ErrorKind, Read, Write};
+/// use mock_io::sync::{FlushMode, MockStream};
+///
+/// let (mut client, mut server) = MockStream::pair();
+/// client.set_flush_mode(FlushMode::Buffered { threshold: 1024 });
+/// server.set_nonblocking(true).unwrap();
+///
+/// client.write_all(b"hello").unwrap();
+///
+/// let mut buf = [0; 5];
+/// assert_eq!(ErrorKind::WouldBlock, server.read(&mut buf).unwrap_err().kind());
+///
+/// client.flush().unwrap();
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"hello", &buf);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FlushMode {
+    /// Data is delivered to the peer as soon as it is written (default)
+    #[default]
+    Immediate,
+    /// Data is delivered to the peer only after `flush` or once the number of buffered bytes reaches `threshold`.
+    /// Unflushed data is discarded when the stream is dropped.
+    Buffered {
+        /// Number of buffered bytes after which data is delivered without waiting for `flush`
+        threshold: usize,
+    },
+    /// Same as `Buffered`, but dropping the stream while it holds unflushed data is recorded and makes
+    /// `Controller::assert_flushed` panic (so that the test fails even if the stream is dropped on another thread)
+    Strict {
+        /// Number of buffered bytes after which data is delivered without waiting for `flush`
+        threshold: usize,
+    },
+}
+
+/// Buffer of data written to a mock stream which is not yet delivered to the peer (shared by clones of a write half)
+#[derive(Debug, Clone)]
+pub(crate) struct FlushBuffer {
+    inner: Arc<Mutex<FlushState>>,
+}
+
+#[derive(Debug)]
+struct FlushState {
+    mode: FlushMode,
+    bytes: Vec<u8>,
+    unflushed: Unflushed,
+}
+
+impl FlushBuffer {
+    /// Creates an empty buffer which records unflushed data dropped in strict mode in given log
+    pub(crate) fn new(unflushed: Unflushed) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(FlushState {
+                mode: Default::default(),
+                bytes: Default::default(),
+                unflushed,
+            })),
+        }
+    }
+
+    pub(crate) fn mode(&self) -> FlushMode {
+        self.lock().mode
+    }
+
+    pub(crate) fn set_mode(&self, mode: FlushMode) {
+        self.lock().mode = mode;
+    }
+
+    /// Buffers given bytes and returns the bytes to be delivered to the peer right away (if any)
+    pub(crate) fn push(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let mut state = self.lock();
+
+        let threshold = match state.mode {
+            FlushMode::Immediate if state.bytes.is_empty() => return Some(bytes.to_vec()),
+            FlushMode::Immediate => 0,
+            FlushMode::Buffered { threshold } | FlushMode::Strict { threshold } => threshold,
+        };
+
+        state.bytes.extend_from_slice(bytes);
+
+        if state.bytes.len() >= threshold {
+            Some(mem::take(&mut state.bytes))
+        } else {
+            None
+        }
+    }
+
+    /// Returns all the buffered bytes (if any)
+    pub(crate) fn flush(&self) -> Option<Vec<u8>> {
+        let mut state = self.lock();
+
+        if state.bytes.is_empty() {
+            None
+        } else {
+            Some(mem::take(&mut state.bytes))
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FlushState> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Drop for FlushState {
+    fn drop(&mut self) {
+        if matches!(self.mode, FlushMode::Strict { .. }) && !self.bytes.is_empty() {
+            self.unflushed.record(self.bytes.len());
+        }
+    }
+}
+
+/// Log of the unflushed data dropped by the streams of a connection in strict flush mode (shared by both streams and
+/// their controllers)
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Unflushed {
+    inner: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Unflushed {
+    fn record(&self, len: usize) {
+        self.lock().push(len);
+    }
+
+    /// Panics if any stream was dropped while holding unflushed data
+    pub(crate) fn assert_empty(&self) {
+        let lens = self.lock().clone();
+
+        if let Some(len) = lens.first() {
+            panic!("mock stream dropped with {} bytes of unflushed data", len);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<usize>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
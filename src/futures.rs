@@ -1,3 +1,27 @@
 //! Mock IO stream and listener in async context
+pub use crate::controller::{Controller, Direction};
+pub use crate::datagram::DatagramFaults;
+pub use crate::flush::FlushMode;
+pub use crate::intercept::{Action, Interceptor};
+pub use crate::lifecycle::ConnectionEvent;
 pub use crate::mock_listener::futures::*;
+pub use crate::mock_peer::futures::*;
+pub use crate::mock_proxy::futures::*;
+pub use crate::mock_seqpacket::futures::*;
+pub use crate::mock_server::futures::*;
 pub use crate::mock_stream::futures::*;
+pub use crate::mock_udp_socket::futures::*;
+pub use crate::mock_unix_listener::futures::*;
+pub use crate::mock_unix_stream::futures::*;
+pub use crate::peer::Peer;
+pub use crate::recording::{RecordedEvent, Recording};
+pub use crate::script::{ChunkPolicy, Fault, FaultPlan, Script};
+pub use crate::server::{Codec, LineCodec, Reply};
+pub use crate::simulation::Simulation;
+pub use crate::socket_options::SocketOptions;
+pub use crate::spawn::{spawn_accept_loop, spawn_echo, Task};
+pub use crate::stats::Stats;
+pub use crate::timeline::{Matcher, Side, Timeline};
+#[cfg(feature = "rustls")]
+pub use crate::tls::{TestCa, TlsFault, TlsOptions};
+pub use crate::unix::{Ancillary, Credentials, UnixSocketAddr};
@@ -0,0 +1,45 @@
+//! Interceptors used by mock proxies for observing and modifying data in flight
+use std::time::Duration;
+
+/// Action taken by a mock proxy on a chunk of data read from one side of a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Action {
+    /// Forwards given bytes to the other side (may differ from the bytes read)
+    Forward(Vec<u8>),
+    /// Forwards given bytes to the other side after waiting for given duration
+    Delay(Duration, Vec<u8>),
+    /// Silently drops the bytes read
+    Drop,
+    /// Resets the connection on both sides of the proxy
+    Reset,
+}
+
+/// Callback invoked by a mock proxy for every chunk of data flowing in one direction
+///
+/// Implemented for all `FnMut(Vec<u8>) -> Action` closures.
+///
+/// ```rust
+/// use mock_io::sync::{Action, Interceptor};
+///
+/// let mut uppercase = |bytes: Vec<u8>| Action::Forward(bytes.to_ascii_uppercase());
+/// assert_eq!(Action::Forward(b"HELLO".to_vec()), uppercase.intercept(b"hello".to_vec()));
+/// ```
+pub trait Interceptor: Send + 'static {
+    /// Returns the action to take on the bytes read from one side of the connection
+    fn intercept(&mut self, bytes: Vec<u8>) -> Action;
+}
+
+impl<F> Interceptor for F
+where
+    F: FnMut(Vec<u8>) -> Action + Send + 'static,
+{
+    fn intercept(&mut self, bytes: Vec<u8>) -> Action {
+        self(bytes)
+    }
+}
+
+/// Interceptor which forwards all the data as is
+pub(crate) fn forward(bytes: Vec<u8>) -> Action {
+    Action::Forward(bytes)
+}
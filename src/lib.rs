@@ -44,18 +44,113 @@
 //!   - **Disabled** by default
 //! - `async-tokio`: Enables async mock IO stream and listener (using `tokio::io::{AsyncRead, AsyncWrite}`)
 //!   - **Disabled** by default
+//! - `proptest`: Enables `proptest` strategies for chunk policies, fault plans, links and scripts
+//!   - **Disabled** by default
+//! - `arbitrary`: Enables `arbitrary` implementations for sessions played by mock peers (for fuzzing)
+//!   - **Disabled** by default
+//! - `tokio-util`: Enables `tokio_util::codec` helpers for tokio mock IO streams (implies `async-tokio`)
+//!   - **Disabled** by default
+//! - `rustls`: Enables wrapping sync mock IO streams in TLS (using `rustls`) with a pre-generated test certificate
+//!   authority (implies `sync`)
+//!   - **Disabled** by default
+//! - `embedded`: Enables `no_std` + `alloc` mock IO stream (using `embedded_io::{Read, Write}` and their async
+//!   counterparts)
+//!   - **Disabled** by default
+//! - `std`: Enables the parts of this crate which depend on `std` (implied by `sync`, `async-futures` and
+//!   `async-tokio`, so the crate is `no_std` with only `embedded` enabled)
+//!   - **Enabled** by default
 //!
 //! > Note: Some functions in this crate returns a `Future`. So, you'll need an executor to drive `Future`s returned
-//! from these functions. `async-std` and `tokio` are two popular options.
+//! > from these functions. `async-std` and `tokio` are two popular options.
+//! > The `async-futures` backend does not depend on any runtime (it is tested with `async-std`, `smol` and
+//! > `futures::executor`), and its helpers for spawning mock peers take a spawner closure instead.
+//!
+//! ### Model checking with `loom`
+//!
+//! When compiled with `RUSTFLAGS="--cfg loom"`, the `sync` and `async-tokio` backends use the locks, atomics and
+//! channels built on [`loom`](https://docs.rs/loom) internally, so that code using mock streams and listeners
+//! (including peers going away) can be model checked inside `loom::model` (asynchronous code can be driven by
+//! `loom::future::block_on`). `loom` does not model time, so read timeouts never expire in this mode and delays of
+//! links and `tokio` timers are not modeled. `async-futures` backend and tokio `TcpBridge` are not available.
 #![cfg_attr(feature = "doc", feature(doc_cfg))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "embedded")]
+extern crate alloc;
 
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod controller;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod datagram;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod flush;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod intercept;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod lifecycle;
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+mod link;
+mod mock_framed;
 mod mock_listener;
+mod mock_peer;
+mod mock_proxy;
+mod mock_seqpacket;
+mod mock_server;
+mod mock_session;
 mod mock_stream;
+#[cfg(feature = "rustls")]
+mod mock_tls;
+mod mock_udp_socket;
+mod mock_unix_listener;
+mod mock_unix_stream;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod pcapng;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod peer;
+#[cfg(feature = "embedded")]
+mod pipe;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod primitives;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod recording;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod rng;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod script;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod server;
+#[cfg(feature = "sync")]
+mod session;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod simulation;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod socket_options;
+#[cfg(feature = "async-futures")]
+mod spawn;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod stats;
+mod tcp_bridge;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod timeline;
+#[cfg(feature = "rustls")]
+mod tls;
+#[cfg(any(feature = "async-futures", feature = "async-tokio", feature = "sync"))]
+mod unix;
 
+#[cfg(feature = "embedded")]
+#[cfg_attr(feature = "doc", doc(cfg(feature = "embedded")))]
+pub mod embedded;
+#[cfg(feature = "std")]
 pub mod error;
 #[cfg(feature = "async-futures")]
 #[cfg_attr(feature = "doc", doc(cfg(feature = "async-futures")))]
 pub mod futures;
+#[cfg(all(
+    feature = "proptest",
+    any(feature = "async-futures", feature = "async-tokio", feature = "sync")
+))]
+#[cfg_attr(feature = "doc", doc(cfg(feature = "proptest")))]
+pub mod strategy;
 #[cfg(feature = "sync")]
 #[cfg_attr(feature = "doc", doc(cfg(feature = "sync")))]
 pub mod sync;
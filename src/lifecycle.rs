@@ -0,0 +1,170 @@
+//! Lifecycle events of the connections going through a mock IO listener
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, PoisonError},
+    time::Instant,
+};
+
+use crate::{
+    primitives::{Mutex, MutexGuard},
+    timeline::Side,
+};
+
+/// Lifecycle event of a connection going through a mock IO listener
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionEvent {
+    /// Client connected to the listener
+    Connect {
+        /// ID of the connection (assigned in the order of connects, starting from zero; refused connects use up an
+        /// ID as well)
+        id: u64,
+        /// Time of the connect
+        at: Instant,
+    },
+    /// Listener accepted the connection
+    Accept {
+        /// ID of the connection
+        id: u64,
+        /// Time of the accept
+        at: Instant,
+    },
+    /// One side of the connection was closed (i.e., its write side was shut down or its stream or write half was
+    /// dropped)
+    Close {
+        /// ID of the connection
+        id: u64,
+        /// Side which was closed (the stream returned by `connect` is the client)
+        side: Side,
+        /// Time of the close
+        at: Instant,
+    },
+}
+
+impl ConnectionEvent {
+    /// Returns the ID of the connection of the event
+    pub fn id(&self) -> u64 {
+        match self {
+            Self::Connect { id, .. } | Self::Accept { id, .. } | Self::Close { id, .. } => *id,
+        }
+    }
+
+    /// Returns the time of the event
+    pub fn at(&self) -> Instant {
+        match self {
+            Self::Connect { at, .. } | Self::Accept { at, .. } | Self::Close { at, .. } => *at,
+        }
+    }
+}
+
+type Hook = Arc<dyn Fn(&ConnectionEvent) + Send + Sync>;
+
+/// Log of the lifecycle events of all the connections going through a listener (shared by the listener and its
+/// handle)
+#[derive(Clone)]
+pub(crate) struct Lifecycle {
+    inner: Arc<Mutex<LifecycleState>>,
+    clock: fn() -> Instant,
+}
+
+#[derive(Default)]
+struct LifecycleState {
+    next_id: u64,
+    /// IDs allocated to connections whose connect is not recorded yet
+    pending: HashSet<u64>,
+    events: Vec<ConnectionEvent>,
+    hooks: Vec<Hook>,
+}
+
+impl Lifecycle {
+    /// Creates a new log which timestamps events using given clock
+    pub(crate) fn new(clock: fn() -> Instant) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Default::default())),
+            clock,
+        }
+    }
+
+    pub(crate) fn events(&self) -> Vec<ConnectionEvent> {
+        self.lock().events.clone()
+    }
+
+    pub(crate) fn on_event(&self, hook: impl Fn(&ConnectionEvent) + Send + Sync + 'static) {
+        self.lock().hooks.push(Arc::new(hook));
+    }
+
+    /// Allocates the ID of a new connection (nothing is recorded for the connection until its connect is)
+    pub(crate) fn allocate(&self) -> u64 {
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.pending.insert(id);
+        id
+    }
+
+    /// Records the connect of given connection once it is handed over to the listener (it is recorded at most once)
+    pub(crate) fn connect(&self, id: u64) {
+        let mut state = self.lock();
+
+        if state.pending.remove(&id) {
+            let at = (self.clock)();
+            self.push(state, ConnectionEvent::Connect { id, at });
+        }
+    }
+
+    pub(crate) fn accept(&self, id: u64) {
+        // The listener may accept the connection before `connect` returns
+        self.connect(id);
+
+        self.record(ConnectionEvent::Accept {
+            id,
+            at: (self.clock)(),
+        });
+    }
+
+    pub(crate) fn close(&self, id: u64, side: Side) {
+        let state = self.lock();
+
+        // Streams of refused connects are not recorded
+        if !state.pending.contains(&id) {
+            let at = (self.clock)();
+            self.push(state, ConnectionEvent::Close { id, side, at });
+        }
+    }
+
+    /// Records given event and passes it to all the hooks (hooks are called without holding the lock, so they may
+    /// inspect the log)
+    fn record(&self, event: ConnectionEvent) {
+        self.push(self.lock(), event);
+    }
+
+    fn push(&self, mut state: MutexGuard<'_, LifecycleState>, event: ConnectionEvent) {
+        state.events.push(event.clone());
+        let hooks = state.hooks.clone();
+        drop(state);
+
+        for hook in hooks {
+            hook(&event);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LifecycleState> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lifecycle")
+            .field("events", &self.lock().events)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Connection of a pair of mock IO streams as seen by the lifecycle log of the listener it went through
+#[derive(Debug, Clone)]
+pub(crate) struct Tracked {
+    pub(crate) lifecycle: Lifecycle,
+    pub(crate) id: u64,
+}
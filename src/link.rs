@@ -0,0 +1,131 @@
+//! Model of the link between a pair of mock IO streams
+use std::{convert::TryFrom, ops::Add, time::Duration};
+
+use crate::rng::Rng;
+
+/// Model of a one-way link carrying data between a pair of mock IO streams
+///
+/// By default, a link delivers data instantly. Every write is delivered as a whole after it has been transmitted at
+/// the configured rate and has spent the configured delay (plus a random jitter) on the link. Data is never
+/// reordered, i.e., jitter never makes a write overtake an earlier one.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// use mock_io::sync::{Link, MockStream};
+///
+/// let uplink = Link::new().delay(Duration::from_millis(20)).rate(1024);
+/// let downlink = Link::new()
+///     .delay(Duration::from_millis(10))
+///     .jitter(Duration::from_millis(5));
+///
+/// let (client, server) = MockStream::pair_with_links(uplink, downlink);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Link {
+    delay: Duration,
+    jitter: Duration,
+    rate: Option<u64>,
+    seed: u64,
+}
+
+impl Link {
+    /// Creates a new link which delivers data instantly
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the one-way delay of the link
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the maximum random jitter added to the delay of every write
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Limits the rate of the link to given number of bytes per second (at least one)
+    pub fn rate(mut self, bytes_per_second: u64) -> Self {
+        self.rate = Some(bytes_per_second.max(1));
+        self
+    }
+
+    /// Sets the seed of the random number generator used for jitter
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub(crate) fn is_instant(&self) -> bool {
+        self.delay == Duration::ZERO && self.jitter == Duration::ZERO && self.rate.is_none()
+    }
+
+    fn transmission_time(&self, len: usize) -> Duration {
+        match self.rate {
+            Some(rate) => {
+                let nanos = (len as u128 * 1_000_000_000) / u128::from(rate);
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// State of a link, used by write halves to compute delivery time of writes
+#[derive(Debug)]
+pub(crate) struct LinkState<I> {
+    link: Link,
+    rng: Rng,
+    busy_until: Option<I>,
+    last_delivery: Option<I>,
+}
+
+impl<I> LinkState<I>
+where
+    I: Copy + Ord + Add<Duration, Output = I>,
+{
+    /// Returns `None` if the link delivers data instantly
+    pub(crate) fn new(link: Link) -> Option<Self> {
+        if link.is_instant() {
+            return None;
+        }
+
+        Some(Self {
+            rng: Rng::new(link.seed),
+            link,
+            busy_until: None,
+            last_delivery: None,
+        })
+    }
+
+    /// Returns the time at which a write of `len` bytes at `now` is delivered to the peer
+    pub(crate) fn schedule(&mut self, now: I, len: usize) -> I {
+        let start = match self.busy_until {
+            Some(busy_until) if busy_until > now => busy_until,
+            _ => now,
+        };
+        let transmitted = start + self.link.transmission_time(len);
+        self.busy_until = Some(transmitted);
+
+        let mut delivery = transmitted + self.link.delay + self.jitter();
+
+        if let Some(last_delivery) = self.last_delivery {
+            delivery = delivery.max(last_delivery);
+        }
+
+        self.last_delivery = Some(delivery);
+        delivery
+    }
+
+    fn jitter(&mut self) -> Duration {
+        let max = self.link.jitter.as_nanos() as u64;
+
+        if max == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos(self.rng.next_u64() % (max + 1))
+    }
+}
@@ -0,0 +1,2 @@
+#[cfg(feature = "tokio-util")]
+pub mod tokio;
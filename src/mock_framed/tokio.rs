@@ -0,0 +1,162 @@
+use std::fmt::Debug;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use crate::tokio::MockStream;
+
+impl MockStream {
+    /// Wraps the stream in a `Framed` with given codec
+    pub fn framed<C>(self, codec: C) -> Framed<Self, C> {
+        Framed::new(self, codec)
+    }
+
+    /// Creates a pair of connected mock streams where first stream is wrapped in a `Framed` with given codec and
+    /// second stream is driven by a scripted peer using a clone of the codec
+    pub fn framed_pair<C, I>(codec: C) -> (Framed<Self, C>, FramedPeer<C, I>)
+    where
+        C: Decoder + Encoder<I> + Clone,
+    {
+        let (stream_1, stream_2) = Self::pair();
+        (
+            stream_1.framed(codec.clone()),
+            FramedPeer::new(stream_2, codec),
+        )
+    }
+}
+
+/// Scripted peer of a mock stream which speaks in frames of a codec
+///
+/// The peer plays its steps in order when it is run: it decodes the next frame and compares it with the expected one
+/// on every `expect` step, and encodes a frame on every `send` step. Run panics with the decoded frames if they do not
+/// match the expected ones.
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use futures_util::{SinkExt, StreamExt};
+/// use mock_io::tokio::MockStream;
+/// use tokio_util::codec::LinesCodec;
+///
+/// let (mut client, peer) = MockStream::framed_pair(LinesCodec::new());
+/// let peer = tokio::spawn(peer.expect("ping").send("pong").run());
+///
+/// client.send("ping").await.unwrap();
+/// assert_eq!("pong", client.next().await.unwrap().unwrap());
+///
+/// peer.await.unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FramedPeer<C: Decoder, I> {
+    framed: Framed<MockStream, C>,
+    steps: Vec<FrameStep<C::Item, I>>,
+}
+
+#[derive(Debug)]
+enum FrameStep<T, I> {
+    Expect(T),
+    Send(I),
+}
+
+impl<C, I> FramedPeer<C, I>
+where
+    C: Decoder + Encoder<I>,
+{
+    /// Creates a new scripted peer which speaks in frames of given codec over given stream
+    pub fn new(stream: MockStream, codec: C) -> Self {
+        Self {
+            framed: stream.framed(codec),
+            steps: Default::default(),
+        }
+    }
+
+    /// Expects the next frame received from the stream to be equal to given frame
+    pub fn expect(mut self, frame: impl Into<C::Item>) -> Self {
+        self.steps.push(FrameStep::Expect(frame.into()));
+        self
+    }
+
+    /// Sends given frame to the stream
+    pub fn send(mut self, frame: I) -> Self {
+        self.steps.push(FrameStep::Send(frame));
+        self
+    }
+
+    /// Plays all the steps of the peer and returns the underlying `Framed` (e.g., to continue the conversation)
+    ///
+    /// # Panics
+    ///
+    /// Panics if a received frame does not match the expected one, if a frame cannot be decoded or encoded or if the
+    /// stream is closed before all the expected frames are received.
+    pub async fn run(self) -> Framed<MockStream, C>
+    where
+        C::Item: Debug + PartialEq,
+        <C as Decoder>::Error: Debug,
+        <C as Encoder<I>>::Error: Debug,
+    {
+        let mut framed = self.framed;
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            match step {
+                FrameStep::Expect(expected) => match framed.next().await {
+                    Some(Ok(frame)) => assert_eq!(
+                        expected, frame,
+                        "unexpected frame received at step {}",
+                        index
+                    ),
+                    Some(Err(error)) => {
+                        panic!("failed to decode frame at step {}: {:?}", index, error)
+                    }
+                    None => panic!(
+                        "stream closed at step {} while expecting frame {:?}",
+                        index, expected
+                    ),
+                },
+                FrameStep::Send(frame) => {
+                    if let Err(error) = framed.send(frame).await {
+                        panic!("failed to send frame at step {}: {:?}", index, error)
+                    }
+                }
+            }
+        }
+
+        framed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::{LengthDelimitedCodec, LinesCodec};
+
+    #[tokio::test]
+    async fn check_framed_peer() {
+        let (mut client, peer) = MockStream::framed_pair(LinesCodec::new());
+        let peer = tokio::spawn(peer.expect("hello").send("world").run());
+
+        client.send("hello").await.unwrap();
+        assert_eq!("world", client.next().await.unwrap().unwrap());
+        peer.await.unwrap();
+
+        let (mut client, peer) = MockStream::framed_pair(LengthDelimitedCodec::new());
+        let peer = tokio::spawn(peer.expect(&b"hello"[..]).send(Bytes::from("world")).run());
+
+        client.send(Bytes::from("hello")).await.unwrap();
+        assert_eq!(
+            BytesMut::from("world"),
+            client.next().await.unwrap().unwrap()
+        );
+        peer.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = r#"right: "world""#)]
+    async fn check_framed_peer_mismatch() {
+        let (mut client, peer) = MockStream::framed_pair::<_, String>(LinesCodec::new());
+        client.send("world").await.unwrap();
+        peer.expect("hello").run().await;
+    }
+}
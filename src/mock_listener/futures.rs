@@ -1,34 +1,116 @@
+use std::{sync::PoisonError, time::Instant};
+
 use async_channel::{unbounded, Receiver, Sender};
 
-use crate::{error::Error, futures::MockStream};
+use crate::{
+    error::{Error, Inner},
+    futures::{ConnectionEvent, MockStream, Simulation, Stats},
+    lifecycle::Lifecycle,
+    stats::Aggregate,
+};
 
 #[derive(Debug)]
 /// Asynchronous mock IO listener
 pub struct MockListener {
     receiver: Receiver<MockStream>,
+    lifecycle: Lifecycle,
 }
 
 impl MockListener {
     /// Creates a new asynchronous mock listener
     pub fn new() -> (Self, Handle) {
+        Self::with(None)
+    }
+
+    /// Creates a new asynchronous mock listener whose connections (and the data sent over them) are scheduled by given
+    /// simulation
+    pub fn with_simulation(simulation: &Simulation) -> (Self, Handle) {
+        Self::with(Some((simulation.clone(), simulation.source())))
+    }
+
+    fn with(simulation: Option<(Simulation, u64)>) -> (Self, Handle) {
         let (sender, receiver) = unbounded();
 
-        (Self { receiver }, Handle { sender })
+        let lifecycle = Lifecycle::new(Instant::now);
+
+        (
+            Self {
+                receiver,
+                lifecycle: lifecycle.clone(),
+            },
+            Handle {
+                sender,
+                stats: Default::default(),
+                simulation,
+                lifecycle,
+            },
+        )
     }
 
     /// Accept a new connection. Returns a mock stream supplied by the sender
     pub async fn accept(&self) -> Result<MockStream, Error> {
-        self.receiver.recv().await.map_err(Into::into)
+        let stream = self.receiver.recv().await?;
+
+        if let Some(id) = stream.connection_id() {
+            self.lifecycle.accept(id);
+        }
+
+        Ok(stream)
     }
 }
 
 /// Handle for synchronous mock IO listener used to connect to the listener
 pub struct Handle {
     sender: Sender<MockStream>,
+    stats: Aggregate,
+    simulation: Option<(Simulation, u64)>,
+    lifecycle: Lifecycle,
 }
 
 impl Handle {
+    /// Returns a snapshot of the aggregate statistics of all the streams accepted by the listener
+    pub fn stats(&self) -> Stats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the lifecycle events of all the connections going through the listener so far
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        self.lifecycle.events()
+    }
+
+    /// Registers a hook which is called with every lifecycle event of the connections going through the listener
+    /// (synchronously, by the thread or task which caused the event)
+    pub fn on_event(&self, hook: impl Fn(&ConnectionEvent) + Send + Sync + 'static) {
+        self.lifecycle.on_event(hook)
+    }
+
+    pub(crate) fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+
+    pub(crate) fn aggregate(&self) -> Aggregate {
+        self.stats.clone()
+    }
+
+    pub(crate) fn simulation(&self) -> Option<&Simulation> {
+        self.simulation.as_ref().map(|(simulation, _)| simulation)
+    }
+
+    /// Sends a mock stream to the listener (queued in the simulation if the listener is simulated)
     pub(crate) async fn send(&self, mock_stream: MockStream) -> Result<(), Error> {
-        self.sender.send(mock_stream).await.map_err(Into::into)
+        match &self.simulation {
+            Some(_) if self.sender.is_closed() => Err(Inner::AsyncSendError.into()),
+            Some((simulation, source)) => {
+                let sender = self.sender.clone();
+                simulation.schedule(*source, move || {
+                    let _ = sender.try_send(mock_stream);
+                });
+                Ok(())
+            }
+            None => self.sender.send(mock_stream).await.map_err(Into::into),
+        }
     }
 }
@@ -1,35 +1,120 @@
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::{fmt, sync::PoisonError, time::Instant};
 
-use crate::{error::Error, sync::MockStream};
+use crate::{
+    error::Error,
+    lifecycle::Lifecycle,
+    primitives::mpsc::{channel, Receiver, Sender},
+    stats::Aggregate,
+    sync::{ConnectionEvent, MockStream, Simulation, Stats},
+};
 
-#[derive(Debug)]
 /// Synchronous mock IO listener
 pub struct MockListener {
     receiver: Receiver<MockStream>,
+    lifecycle: Lifecycle,
 }
 
 impl MockListener {
     /// Creates a new synchronous mock listener
     pub fn new() -> (Self, Handle) {
+        Self::with(None)
+    }
+
+    /// Creates a new synchronous mock listener whose connections (and the data sent over them) are scheduled by given
+    /// simulation
+    pub fn with_simulation(simulation: &Simulation) -> (Self, Handle) {
+        Self::with(Some((simulation.clone(), simulation.source())))
+    }
+
+    fn with(simulation: Option<(Simulation, u64)>) -> (Self, Handle) {
         let (sender, receiver) = channel();
 
-        (Self { receiver }, Handle { sender })
+        let lifecycle = Lifecycle::new(Instant::now);
+
+        (
+            Self {
+                receiver,
+                lifecycle: lifecycle.clone(),
+            },
+            Handle {
+                sender,
+                stats: Default::default(),
+                simulation,
+                lifecycle,
+            },
+        )
     }
 
     /// Accept a new connection. Returns a mock stream supplied by the sender
     pub fn accept(&self) -> Result<MockStream, Error> {
-        self.receiver.recv().map_err(Into::into)
+        let stream = self.receiver.recv()?;
+
+        if let Some(id) = stream.connection_id() {
+            self.lifecycle.accept(id);
+        }
+
+        Ok(stream)
+    }
+}
+
+impl fmt::Debug for MockListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockListener").finish_non_exhaustive()
     }
 }
 
 /// Handle for synchronous mock IO listener used to connect to the listener
 pub struct Handle {
     sender: Sender<MockStream>,
+    stats: Aggregate,
+    simulation: Option<(Simulation, u64)>,
+    lifecycle: Lifecycle,
 }
 
 impl Handle {
+    /// Returns a snapshot of the aggregate statistics of all the streams accepted by the listener
+    pub fn stats(&self) -> Stats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the lifecycle events of all the connections going through the listener so far
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        self.lifecycle.events()
+    }
+
+    /// Registers a hook which is called with every lifecycle event of the connections going through the listener
+    /// (synchronously, by the thread or task which caused the event)
+    pub fn on_event(&self, hook: impl Fn(&ConnectionEvent) + Send + Sync + 'static) {
+        self.lifecycle.on_event(hook)
+    }
+
+    pub(crate) fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+
+    pub(crate) fn aggregate(&self) -> Aggregate {
+        self.stats.clone()
+    }
+
+    pub(crate) fn simulation(&self) -> Option<&Simulation> {
+        self.simulation.as_ref().map(|(simulation, _)| simulation)
+    }
+
+    /// Sends a mock stream to the listener (queued in the simulation if the listener is simulated)
     pub(crate) fn send(&self, mock_stream: MockStream) -> Result<(), Error> {
-        self.sender.send(mock_stream).map_err(Into::into)
+        match &self.simulation {
+            Some((simulation, source)) => {
+                let sender = self.sender.clone();
+                simulation.schedule(*source, move || {
+                    let _ = sender.send(mock_stream);
+                });
+                Ok(())
+            }
+            None => self.sender.send(mock_stream).map_err(Into::into),
+        }
     }
 }
 
@@ -39,27 +124,251 @@ mod tests {
 
     use std::{
         io::{Read, Write},
+        sync::{mpsc, Mutex},
         thread,
     };
 
+    use crate::sync::Side;
+
     #[test]
     fn check_listener_flow() {
         let (listener, handle) = MockListener::new();
 
         thread::spawn(move || {
             let mut stream = MockStream::connect(&handle).unwrap();
-            stream.write(&1u64.to_be_bytes()).unwrap();
-            stream.write(&2u64.to_be_bytes()).unwrap();
+            assert_eq!(8, stream.write(&1u64.to_be_bytes()).unwrap());
+            assert_eq!(8, stream.write(&2u64.to_be_bytes()).unwrap());
         });
 
         while let Ok(mut stream) = listener.accept() {
             let mut buf = [0; 8];
 
-            stream.read(&mut buf).unwrap();
+            assert_eq!(8, stream.read(&mut buf).unwrap());
             assert_eq!(1u64.to_be_bytes(), buf);
 
-            stream.read(&mut buf).unwrap();
+            assert_eq!(8, stream.read(&mut buf).unwrap());
             assert_eq!(2u64.to_be_bytes(), buf);
         }
     }
+
+    #[test]
+    fn check_handle_stats() {
+        let (listener, handle) = MockListener::new();
+
+        for _ in 0..2 {
+            let mut client = MockStream::connect(&handle).unwrap();
+            client.write_all(&[1, 2, 3]).unwrap();
+
+            let mut server = listener.accept().unwrap();
+            let mut buf = [0; 3];
+            server.read_exact(&mut buf).unwrap();
+            server.flush().unwrap();
+        }
+
+        let before = handle.stats();
+        assert_eq!(6, before.bytes_read);
+        assert_eq!(2, before.messages_read);
+        assert_eq!(2, before.flush_calls);
+        assert_eq!(0, before.bytes_written);
+
+        let mut client = MockStream::connect(&handle).unwrap();
+        client.write_all(&[4]).unwrap();
+
+        let mut server = listener.accept().unwrap();
+        server.write_all(&[5, 6]).unwrap();
+
+        let stats = handle.stats().since(&before);
+        assert_eq!(0, stats.bytes_read);
+        assert_eq!(2, stats.bytes_written);
+        assert_eq!(2, stats.max_pending);
+    }
+
+    #[test]
+    fn check_simulated_listener() {
+        let simulation = Simulation::new(7);
+        let (listener, handle) = MockListener::with_simulation(&simulation);
+        let mut buf = [0; 3];
+
+        let mut client = MockStream::connect(&handle).unwrap();
+        client.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(2, simulation.pending());
+        assert_eq!(2, simulation.run_until_idle());
+
+        let mut server = listener.accept().unwrap();
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!([1, 2, 3], buf);
+
+        server.write_all(&[4]).unwrap();
+        assert_eq!(1, simulation.pending());
+        simulation.run_until_idle();
+
+        client.read_exact(&mut buf[..1]).unwrap();
+        assert_eq!(4, buf[0]);
+    }
+
+    #[test]
+    fn check_connect_to_dropped_listener() {
+        let (listener, handle) = MockListener::new();
+        drop(listener);
+
+        let error = MockStream::connect(&handle).err().unwrap();
+        assert_eq!(crate::error::ErrorKind::StreamConnectError, error.kind());
+        assert!(handle.events().is_empty());
+    }
+
+    #[test]
+    fn check_lifecycle_events() {
+        let (listener, handle) = MockListener::new();
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        handle.on_event(move |event| sender.lock().unwrap().send(event.id()).unwrap());
+
+        let client_1 = MockStream::connect(&handle).unwrap();
+        let client_2 = MockStream::connect(&handle).unwrap();
+
+        let server_1 = listener.accept().unwrap();
+        drop(client_1);
+        drop(listener.accept().unwrap());
+        drop(server_1);
+        drop(client_2);
+
+        let events = handle.events();
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|event| match event {
+                ConnectionEvent::Connect { id, .. } => (*id, "connect"),
+                ConnectionEvent::Accept { id, .. } => (*id, "accept"),
+                ConnectionEvent::Close {
+                    id,
+                    side: Side::Client,
+                    ..
+                } => (*id, "client close"),
+                ConnectionEvent::Close { id, .. } => (*id, "server close"),
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (0, "connect"),
+                (1, "connect"),
+                (0, "accept"),
+                (0, "client close"),
+                (1, "accept"),
+                (1, "server close"),
+                (0, "server close"),
+                (1, "client close"),
+            ],
+            kinds
+        );
+        assert!(events.windows(2).all(|pair| pair[0].at() <= pair[1].at()));
+        assert_eq!(
+            events.iter().map(ConnectionEvent::id).collect::<Vec<_>>(),
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_connect_and_accept() {
+        loom::model(|| {
+            let (listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || {
+                let mut stream = MockStream::connect(&handle).unwrap();
+                let mut buf = [0; 3];
+
+                stream.write_all(&[1, 2, 3]).unwrap();
+                stream.read_exact(&mut buf).unwrap();
+                assert_eq!([4, 5, 6], buf);
+            });
+
+            let mut stream = listener.accept().unwrap();
+            let mut buf = [0; 3];
+
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!([1, 2, 3], buf);
+            stream.write_all(&[4, 5, 6]).unwrap();
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_reset_while_accepting() {
+        loom::model(|| {
+            let (listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || {
+                let mut stream = MockStream::connect(&handle).unwrap();
+                stream.write_all(&[1]).unwrap();
+                stream.controller().reset();
+            });
+
+            let mut stream = listener.accept().unwrap();
+            let mut buf = [0; 1];
+
+            match stream.read(&mut buf) {
+                Ok(len) => assert_eq!(1, len),
+                Err(error) => assert_eq!(std::io::ErrorKind::ConnectionReset, error.kind()),
+            }
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_client_while_accepting() {
+        loom::model(|| {
+            let (listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || {
+                let mut stream = MockStream::connect(&handle).unwrap();
+                stream.write_all(&[1]).unwrap();
+            });
+
+            let mut stream = listener.accept().unwrap();
+            let mut buf = [0; 2];
+
+            stream.read_exact(&mut buf[..1]).unwrap();
+            assert_eq!(1, buf[0]);
+            assert_eq!(0, stream.read(&mut buf).unwrap());
+            assert!(listener.accept().is_err());
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_handle_while_accepting() {
+        loom::model(|| {
+            let (listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || drop(handle));
+
+            assert!(listener.accept().is_err());
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_server_while_reading() {
+        loom::model(|| {
+            let (listener, handle) = MockListener::new();
+            let mut stream = MockStream::connect(&handle).unwrap();
+            let server = listener.accept().unwrap();
+
+            let server = loom::thread::spawn(move || drop(server));
+
+            let mut buf = [0; 1];
+            assert_eq!(0, stream.read(&mut buf).unwrap());
+
+            server.join().unwrap();
+        });
+    }
 }
@@ -1,41 +1,127 @@
-use tokio::sync::mpsc::{
-    unbounded_channel as unbounded, UnboundedReceiver as Receiver, UnboundedSender as Sender,
-};
+use std::{sync::PoisonError, time::Instant};
 
-use crate::{error::Error, tokio::MockStream};
+use crate::{
+    error::{Error, Inner},
+    lifecycle::Lifecycle,
+    primitives::unbounded::{
+        unbounded_channel as unbounded, UnboundedReceiver as Receiver, UnboundedSender as Sender,
+    },
+    stats::Aggregate,
+    tokio::{ConnectionEvent, MockStream, Simulation, Stats},
+};
 
 #[derive(Debug)]
 /// Asynchronous mock IO listener
 pub struct MockListener {
     receiver: Receiver<MockStream>,
+    lifecycle: Lifecycle,
 }
 
 impl MockListener {
     /// Creates a new asynchronous mock listener
     pub fn new() -> (Self, Handle) {
+        Self::with(None)
+    }
+
+    /// Creates a new asynchronous mock listener whose connections (and the data sent over them) are scheduled by given
+    /// simulation
+    pub fn with_simulation(simulation: &Simulation) -> (Self, Handle) {
+        Self::with(Some((simulation.clone(), simulation.source())))
+    }
+
+    fn with(simulation: Option<(Simulation, u64)>) -> (Self, Handle) {
         let (sender, receiver) = unbounded();
 
-        (Self { receiver }, Handle { sender })
+        let lifecycle = Lifecycle::new(clock);
+
+        (
+            Self {
+                receiver,
+                lifecycle: lifecycle.clone(),
+            },
+            Handle {
+                sender,
+                stats: Default::default(),
+                simulation,
+                lifecycle,
+            },
+        )
     }
 
     /// Accept a new connection. Returns a mock stream supplied by the sender
     pub async fn accept(&mut self) -> Result<MockStream, Error> {
-        self.receiver
+        let stream = self
+            .receiver
             .recv()
             .await
-            .ok_or(crate::error::Inner::TokioRecvError)
-            .map_err(Into::into)
+            .ok_or(crate::error::Inner::TokioRecvError)?;
+
+        if let Some(id) = stream.connection_id() {
+            self.lifecycle.accept(id);
+        }
+
+        Ok(stream)
     }
 }
 
+/// Returns the current time of the tokio clock (which advances with paused time in tests)
+fn clock() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
 /// Handle for synchronous mock IO listener used to connect to the listener
 pub struct Handle {
     sender: Sender<MockStream>,
+    stats: Aggregate,
+    simulation: Option<(Simulation, u64)>,
+    lifecycle: Lifecycle,
 }
 
 impl Handle {
+    /// Returns a snapshot of the aggregate statistics of all the streams accepted by the listener
+    pub fn stats(&self) -> Stats {
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the lifecycle events of all the connections going through the listener so far
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        self.lifecycle.events()
+    }
+
+    /// Registers a hook which is called with every lifecycle event of the connections going through the listener
+    /// (synchronously, by the thread or task which caused the event)
+    pub fn on_event(&self, hook: impl Fn(&ConnectionEvent) + Send + Sync + 'static) {
+        self.lifecycle.on_event(hook)
+    }
+
+    pub(crate) fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+
+    pub(crate) fn aggregate(&self) -> Aggregate {
+        self.stats.clone()
+    }
+
+    pub(crate) fn simulation(&self) -> Option<&Simulation> {
+        self.simulation.as_ref().map(|(simulation, _)| simulation)
+    }
+
+    /// Sends a mock stream to the listener (queued in the simulation if the listener is simulated)
     pub(crate) fn send(&self, mock_stream: MockStream) -> Result<(), Error> {
-        self.sender.send(mock_stream).map_err(Into::into)
+        match &self.simulation {
+            Some(_) if self.sender.is_closed() => Err(Inner::TokioSendError.into()),
+            Some((simulation, source)) => {
+                let sender = self.sender.clone();
+                simulation.schedule(*source, move || {
+                    let _ = sender.send(mock_stream);
+                });
+                Ok(())
+            }
+            None => self.sender.send(mock_stream).map_err(Into::into),
+        }
     }
 }
 
@@ -43,38 +129,188 @@ impl Handle {
 mod tests {
     use super::*;
 
+    use std::time::Duration;
+
     use tokio::{
         io::{AsyncReadExt, AsyncWriteExt},
-        task,
+        task, time,
     };
 
+    use crate::tokio::Side;
+
     #[tokio::test]
     async fn check_listener_flow() {
         let (mut listener, handle) = MockListener::new();
 
         task::spawn(async move {
             let mut stream = MockStream::connect(&handle).unwrap();
-            stream.write(&1u64.to_be_bytes()).await.unwrap();
-            stream.write(&2u64.to_be_bytes()).await.unwrap();
+            assert_eq!(8, stream.write(&1u64.to_be_bytes()).await.unwrap());
+            assert_eq!(8, stream.write(&2u64.to_be_bytes()).await.unwrap());
         });
 
         while let Ok(mut stream) = listener.accept().await {
             let mut buf = [0; 1];
-            stream.read(&mut buf).await.unwrap();
+            assert_eq!(1, stream.read(&mut buf).await.unwrap());
             assert_eq!([0], buf);
 
             let mut buf = [0; 3];
-            stream.read(&mut buf).await.unwrap();
+            assert_eq!(3, stream.read(&mut buf).await.unwrap());
             assert_eq!([0, 0, 0], buf);
 
             let mut buf = [0; 4];
-            stream.read(&mut buf).await.unwrap();
+            assert_eq!(4, stream.read(&mut buf).await.unwrap());
             assert_eq!([0, 0, 0, 1], buf);
 
             let mut buf = [0; 8];
 
-            stream.read(&mut buf).await.unwrap();
+            assert_eq!(8, stream.read(&mut buf).await.unwrap());
             assert_eq!(2u64.to_be_bytes(), buf);
         }
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn check_idle_connection_closed() {
+        let (mut listener, handle) = MockListener::new();
+
+        task::spawn(async move {
+            let mut stream = listener.accept().await.unwrap();
+            let mut buf = [0; 1];
+            let _ = time::timeout(Duration::from_secs(30), stream.read(&mut buf)).await;
+        });
+
+        let mut client = MockStream::connect(&handle).unwrap();
+        let mut buf = [0; 1];
+        assert_eq!(0, client.read(&mut buf).await.unwrap());
+        drop(client);
+
+        let events = handle.events();
+        let accepted_at = match &events[1] {
+            ConnectionEvent::Accept { id: 0, at } => *at,
+            event => panic!("unexpected event: {:?}", event),
+        };
+
+        match &events[2] {
+            ConnectionEvent::Close {
+                id: 0,
+                side: Side::Server,
+                at,
+            } => {
+                assert_eq!(Duration::from_secs(30), *at - accepted_at)
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+
+        assert!(matches!(
+            events[3],
+            ConnectionEvent::Close {
+                side: Side::Client,
+                ..
+            }
+        ));
+    }
+
+    /// Runs given model with bounded preemptions (polling the streams takes more locks than the blocking calls of the
+    /// `sync` backend, so exploring every interleaving does not finish in reasonable time)
+    #[cfg(loom)]
+    fn model(f: impl Fn() + Send + Sync + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(f);
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_connect_and_accept() {
+        use loom::future::block_on;
+
+        model(|| {
+            let (mut listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || {
+                block_on(async {
+                    let mut stream = MockStream::connect(&handle).unwrap();
+                    let mut buf = [0; 3];
+
+                    stream.write_all(&[1, 2, 3]).await.unwrap();
+                    stream.read_exact(&mut buf).await.unwrap();
+                    assert_eq!([4, 5, 6], buf);
+                })
+            });
+
+            block_on(async {
+                let mut stream = listener.accept().await.unwrap();
+                let mut buf = [0; 3];
+
+                stream.read_exact(&mut buf).await.unwrap();
+                assert_eq!([1, 2, 3], buf);
+                stream.write_all(&[4, 5, 6]).await.unwrap();
+            });
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_client_while_accepting() {
+        use loom::future::block_on;
+
+        model(|| {
+            let (mut listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || {
+                block_on(async {
+                    let mut stream = MockStream::connect(&handle).unwrap();
+                    stream.write_all(&[1]).await.unwrap();
+                })
+            });
+
+            block_on(async {
+                let mut stream = listener.accept().await.unwrap();
+                let mut buf = [0; 2];
+
+                stream.read_exact(&mut buf[..1]).await.unwrap();
+                assert_eq!(1, buf[0]);
+                assert_eq!(0, stream.read(&mut buf).await.unwrap());
+                assert!(listener.accept().await.is_err());
+            });
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_handle_while_accepting() {
+        use loom::future::block_on;
+
+        model(|| {
+            let (mut listener, handle) = MockListener::new();
+
+            let client = loom::thread::spawn(move || drop(handle));
+
+            assert!(block_on(listener.accept()).is_err());
+
+            client.join().unwrap();
+        });
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_drop_server_while_reading() {
+        use loom::future::block_on;
+
+        model(|| {
+            let (mut listener, handle) = MockListener::new();
+            let mut stream = MockStream::connect(&handle).unwrap();
+            let server = block_on(listener.accept()).unwrap();
+
+            let server = loom::thread::spawn(move || drop(server));
+
+            let mut buf = [0; 1];
+            assert_eq!(0, block_on(stream.read(&mut buf)).unwrap());
+
+            server.join().unwrap();
+        });
+    }
 }
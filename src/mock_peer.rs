@@ -0,0 +1,6 @@
+#[cfg(feature = "async-futures")]
+pub mod futures;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "async-tokio")]
+pub mod tokio;
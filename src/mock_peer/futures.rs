@@ -0,0 +1,146 @@
+use std::{future::poll_fn, future::Future, pin::Pin, task::Poll};
+
+use crate::{
+    error::Error,
+    futures::{MockListener, MockStream, Peer, Task},
+    mock_proxy::futures::sleep,
+    peer::{BUFFER_SIZE, POLL_INTERVAL},
+};
+
+impl MockStream {
+    /// Creates a pair of connected mock streams and runs given peer against the second stream on a task spawned using
+    /// given spawner. Returns the first stream.
+    ///
+    /// ```rust
+    /// use futures_util::{AsyncReadExt, AsyncWriteExt};
+    /// use mock_io::futures::{MockStream, Peer};
+    ///
+    /// async_std::task::block_on(async {
+    ///     let spawner = |task| {
+    ///         async_std::task::spawn(task);
+    ///     };
+    ///
+    ///     let mut stream = MockStream::pair_with_peer(Peer::Echo, &spawner);
+    ///     stream.write_all(b"hello").await.unwrap();
+    ///
+    ///     let mut buf = [0; 5];
+    ///     stream.read_exact(&mut buf).await.unwrap();
+    ///     assert_eq!(b"hello", &buf);
+    /// });
+    /// ```
+    pub fn pair_with_peer<S>(peer: Peer, spawner: &S) -> Self
+    where
+        S: Fn(Task),
+    {
+        let (stream_1, stream_2) = Self::pair();
+        spawn_peer(spawner, stream_2, peer);
+        stream_1
+    }
+}
+
+/// Runs given peer against given stream on a task spawned using given spawner. The task finishes when the peer stops.
+pub fn spawn_peer<S>(spawner: &S, stream: MockStream, peer: Peer)
+where
+    S: Fn(Task),
+{
+    spawner(Box::pin(run(stream, peer)));
+}
+
+/// Accepts connections from given listener on a spawned task (until all the handles of the listener are dropped) and
+/// runs given peer against each of them on its own task
+pub fn spawn_listener_peer<S>(spawner: S, listener: MockListener, peer: Peer)
+where
+    S: Fn(Task) + Clone + Send + Sync + 'static,
+{
+    let spawner_clone = spawner.clone();
+
+    spawner(Box::pin(async move {
+        while let Ok(stream) = listener.accept().await {
+            spawn_peer(&spawner_clone, stream, peer.clone());
+        }
+    }));
+}
+
+async fn run(mut stream: MockStream, peer: Peer) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    match peer {
+        Peer::Echo => loop {
+            let len = match stream.receive(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(len) => len,
+            };
+
+            if stream.send(&buf[..len]).is_err() {
+                return;
+            }
+        },
+        Peer::Sink => while let Ok(1..) = stream.receive(&mut buf).await {},
+        Peer::Source(bytes) => {
+            let _ = stream.send(&bytes);
+        }
+        Peer::Repeat(pattern) => {
+            if pattern.is_empty() {
+                return;
+            }
+
+            loop {
+                // Bytes dropped on the way (e.g., by a blackhole) are never pending, so each round is bounded
+                let mut written = 0;
+
+                while written < BUFFER_SIZE && stream.pending() < BUFFER_SIZE as u64 {
+                    if stream.send(&pattern).is_err() {
+                        return;
+                    }
+
+                    written += pattern.len();
+                }
+
+                match try_receive(&mut stream, &mut buf).await {
+                    Some(Ok(0)) | Some(Err(_)) => return,
+                    _ => sleep(POLL_INTERVAL).await,
+                }
+            }
+        }
+        Peer::Discard => {}
+    }
+}
+
+/// Receives data from given stream without waiting. Returns `None` if no data is available yet.
+async fn try_receive(stream: &mut MockStream, buf: &mut [u8]) -> Option<Result<usize, Error>> {
+    let mut receive = Box::pin(stream.receive(buf));
+
+    poll_fn(|cx| match Pin::new(&mut receive).poll(cx) {
+        Poll::Ready(result) => Poll::Ready(Some(result)),
+        Poll::Pending => Poll::Ready(None),
+    })
+    .await
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn check_peers() {
+        async_std::task::block_on(async {
+            let spawner = |task| {
+                async_std::task::spawn(task);
+            };
+
+            let (listener, handle) = MockListener::new();
+            spawn_listener_peer(spawner, listener, Peer::Repeat(b"ab".to_vec()));
+
+            let mut stream = MockStream::connect(&handle).await.unwrap();
+            let mut buf = [0; 3 * BUFFER_SIZE];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert!(buf.chunks(2).all(|chunk| chunk == b"ab"));
+
+            let mut stream = MockStream::pair_with_peer(Peer::Sink, &spawner);
+            stream.write_all(b"hello").await.unwrap();
+            stream.close().await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    peer::{BUFFER_SIZE, POLL_INTERVAL},
+    sync::{MockListener, MockStream, Peer},
+};
+
+impl MockStream {
+    /// Creates a pair of connected mock streams and runs given peer against the second stream on a background thread.
+    /// Returns the first stream.
+    ///
+    /// ```rust
+    /// # use std::io::{Read, Write};
+    /// use mock_io::sync::{MockStream, Peer};
+    ///
+    /// let mut stream = MockStream::pair_with_peer(Peer::Echo);
+    /// stream.write_all(b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// stream.read_exact(&mut buf).unwrap();
+    /// assert_eq!(b"hello", &buf);
+    /// ```
+    pub fn pair_with_peer(peer: Peer) -> Self {
+        let (stream_1, stream_2) = Self::pair();
+        spawn_peer(stream_2, peer);
+        stream_1
+    }
+}
+
+/// Runs given peer against given stream on a background thread. The thread finishes when the peer stops.
+pub fn spawn_peer(stream: MockStream, peer: Peer) -> JoinHandle<()> {
+    thread::spawn(move || run(stream, peer))
+}
+
+/// Accepts connections from given listener on a background thread (until all the handles of the listener are dropped)
+/// and runs given peer against each of them on its own thread
+pub fn spawn_listener_peer(listener: MockListener, peer: Peer) -> JoinHandle<()> {
+    thread::spawn(move || {
+        while let Ok(stream) = listener.accept() {
+            spawn_peer(stream, peer.clone());
+        }
+    })
+}
+
+fn run(mut stream: MockStream, peer: Peer) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    match peer {
+        Peer::Echo => loop {
+            let len = match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(len) => len,
+            };
+
+            if stream.write_all(&buf[..len]).is_err() {
+                return;
+            }
+        },
+        Peer::Sink => while let Ok(1..) = stream.read(&mut buf) {},
+        Peer::Source(bytes) => {
+            let _ = stream.write_all(&bytes);
+        }
+        Peer::Repeat(pattern) => {
+            if pattern.is_empty() || stream.set_nonblocking(true).is_err() {
+                return;
+            }
+
+            loop {
+                // Bytes dropped on the way (e.g., by a blackhole) are never pending, so each round is bounded
+                let mut written = 0;
+
+                while written < BUFFER_SIZE && stream.pending() < BUFFER_SIZE as u64 {
+                    if stream.write_all(&pattern).is_err() {
+                        return;
+                    }
+
+                    written += pattern.len();
+                }
+
+                match stream.read(&mut buf) {
+                    Ok(0) => return,
+                    Err(error) if error.kind() != ErrorKind::WouldBlock => return,
+                    _ => thread::sleep(POLL_INTERVAL),
+                }
+            }
+        }
+        Peer::Discard => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sync::Direction;
+
+    #[test]
+    fn check_peers() {
+        let (listener, handle) = MockListener::new();
+        let server = spawn_listener_peer(listener, Peer::Source(b"hello".to_vec()));
+
+        let mut stream = MockStream::connect(&handle).unwrap();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(b"hello", &buf[..]);
+
+        drop(handle);
+        server.join().unwrap();
+
+        let (mut stream, other) = MockStream::pair();
+        let peer = spawn_peer(other, Peer::Repeat(b"ab".to_vec()));
+
+        let mut buf = [0; 3 * BUFFER_SIZE];
+        stream.read_exact(&mut buf).unwrap();
+        assert!(buf.chunks(2).all(|chunk| chunk == b"ab"));
+
+        drop(stream);
+        peer.join().unwrap();
+
+        // Blackholed bytes are not pending, so the peer keeps writing once the blackhole is lifted
+        let (mut stream, other) = MockStream::pair();
+        let controller = stream.controller();
+        controller.blackhole(Direction::Incoming);
+
+        let peer = spawn_peer(other, Peer::Repeat(b"ab".to_vec()));
+        thread::sleep(4 * POLL_INTERVAL);
+        controller.resume(Direction::Incoming);
+
+        let mut buf = [0; 2];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(b"ab", &buf);
+
+        drop(stream);
+        peer.join().unwrap();
+
+        let (mut stream, other) = MockStream::pair();
+        let peer = spawn_peer(other, Peer::Sink);
+
+        stream.write_all(b"hello").unwrap();
+        drop(stream);
+        peer.join().unwrap();
+
+        let mut stream = MockStream::pair_with_peer(Peer::Discard);
+        assert_eq!(0, stream.read(&mut [0; 5]).unwrap());
+
+        let (mut stream, _other) = MockStream::pair();
+        stream.controller().blackhole(Direction::Outgoing);
+        stream.write_all(b"hello").unwrap();
+        assert_eq!(0, stream.stats().max_pending);
+    }
+}
@@ -0,0 +1,130 @@
+use tokio::{task::JoinHandle, time};
+
+use crate::{
+    peer::{BUFFER_SIZE, POLL_INTERVAL},
+    tokio::{MockListener, MockStream, Peer},
+};
+
+impl MockStream {
+    /// Creates a pair of connected mock streams and runs given peer against the second stream on a task spawned on the
+    /// current tokio runtime. Returns the first stream.
+    ///
+    /// ```rust
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// use mock_io::tokio::{MockStream, Peer};
+    /// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    ///
+    /// let mut stream = MockStream::pair_with_peer(Peer::Echo);
+    /// stream.write_all(b"hello").await.unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// stream.read_exact(&mut buf).await.unwrap();
+    /// assert_eq!(b"hello", &buf);
+    /// # }
+    /// ```
+    pub fn pair_with_peer(peer: Peer) -> Self {
+        let (stream_1, stream_2) = Self::pair();
+        spawn_peer(stream_2, peer);
+        stream_1
+    }
+}
+
+/// Runs given peer against given stream on a task spawned on the current tokio runtime. The task finishes when the
+/// peer stops.
+pub fn spawn_peer(stream: MockStream, peer: Peer) -> JoinHandle<()> {
+    tokio::spawn(run(stream, peer))
+}
+
+/// Accepts connections from given listener on a background task (until all the handles of the listener are dropped)
+/// and runs given peer against each of them on its own task
+pub fn spawn_listener_peer(mut listener: MockListener, peer: Peer) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Ok(stream) = listener.accept().await {
+            spawn_peer(stream, peer.clone());
+        }
+    })
+}
+
+async fn run(mut stream: MockStream, peer: Peer) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    match peer {
+        Peer::Echo => loop {
+            let len = match stream.receive(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(len) => len,
+            };
+
+            if stream.send(&buf[..len]).is_err() {
+                return;
+            }
+        },
+        Peer::Sink => while let Ok(1..) = stream.receive(&mut buf).await {},
+        Peer::Source(bytes) => {
+            let _ = stream.send(&bytes);
+        }
+        Peer::Repeat(pattern) => {
+            if pattern.is_empty() {
+                return;
+            }
+
+            loop {
+                // Bytes dropped on the way (e.g., by a blackhole) are never pending, so each round is bounded
+                let mut written = 0;
+
+                while written < BUFFER_SIZE && stream.pending() < BUFFER_SIZE as u64 {
+                    if stream.send(&pattern).is_err() {
+                        return;
+                    }
+
+                    written += pattern.len();
+                }
+
+                match time::timeout(POLL_INTERVAL, stream.receive(&mut buf)).await {
+                    Ok(Ok(0)) | Ok(Err(_)) => return,
+                    _ => {}
+                }
+            }
+        }
+        Peer::Discard => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn check_peers() {
+        let (listener, handle) = MockListener::new();
+        let server = spawn_listener_peer(listener, Peer::Echo);
+
+        let mut stream = MockStream::connect(&handle).unwrap();
+        stream.write_all(b"hello").await.unwrap();
+
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"hello", &buf);
+
+        drop(handle);
+        server.await.unwrap();
+
+        let (mut stream, other) = MockStream::pair();
+        let peer = spawn_peer(other, Peer::Repeat(b"ab".to_vec()));
+
+        let mut buf = [0; 3 * BUFFER_SIZE];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert!(buf.chunks(2).all(|chunk| chunk == b"ab"));
+
+        drop(stream);
+        peer.await.unwrap();
+
+        let mut stream = MockStream::pair_with_peer(Peer::Source(b"hello".to_vec()));
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(b"hello", &buf[..]);
+    }
+}
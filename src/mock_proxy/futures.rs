@@ -0,0 +1,158 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use async_channel::bounded;
+
+use crate::{
+    error::Error,
+    futures::{
+        Action, Controller, Handle, Interceptor, MockListener, MockStream, ReadHalf, WriteHalf,
+    },
+    intercept::forward,
+};
+
+/// Size of the buffer used for reading data flowing through the proxy
+const BUFFER_SIZE: usize = 8 * 1024;
+
+type SharedInterceptor = Arc<Mutex<Box<dyn Interceptor>>>;
+
+/// Future spawned by a mock proxy for forwarding data in one direction of a connection
+pub type ProxyTask = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Asynchronous mock proxy which sits between clients and a mock IO listener
+///
+/// Every connection accepted by the proxy is connected to the listener of given handle. Data flowing in each
+/// direction is passed through an interceptor which can observe, modify, delay or drop it or reset the connection.
+/// Forwarding tasks are spawned using the spawner supplied to the proxy.
+pub struct MockProxy {
+    listener: MockListener,
+    handle: Handle,
+    spawner: Box<dyn Fn(ProxyTask) + Send + Sync>,
+    client_to_server: SharedInterceptor,
+    server_to_client: SharedInterceptor,
+}
+
+impl MockProxy {
+    /// Creates a new proxy which accepts connections from `listener` and connects them to the listener of `handle`
+    ///
+    /// All the data is forwarded as is until interceptors are set.
+    pub fn new(
+        listener: MockListener,
+        handle: Handle,
+        spawner: impl Fn(ProxyTask) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            listener,
+            handle,
+            spawner: Box::new(spawner),
+            client_to_server: Arc::new(Mutex::new(Box::new(forward))),
+            server_to_client: Arc::new(Mutex::new(Box::new(forward))),
+        }
+    }
+
+    /// Sets the interceptor for data flowing from clients to the server
+    pub fn client_to_server(mut self, interceptor: impl Interceptor) -> Self {
+        self.client_to_server = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Sets the interceptor for data flowing from the server to clients
+    pub fn server_to_client(mut self, interceptor: impl Interceptor) -> Self {
+        self.server_to_client = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Accepts one connection, connects it to the server and spawns tasks forwarding data between them
+    pub async fn accept(&self) -> Result<(), Error> {
+        let client = self.listener.accept().await?;
+        self.forward(client).await
+    }
+
+    /// Accepts connections until all the handles of the proxy's listener are dropped
+    pub async fn run(self) -> Result<(), Error> {
+        while let Ok(client) = self.listener.accept().await {
+            self.forward(client).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn forward(&self, client: MockStream) -> Result<(), Error> {
+        let server = MockStream::connect(&self.handle).await?;
+
+        let controllers = [client.controller(), server.controller()];
+
+        let (client_read, client_write) = client.split();
+        let (server_read, server_write) = server.split();
+
+        (self.spawner)(Box::pin(pipe(
+            client_read,
+            server_write,
+            self.client_to_server.clone(),
+            controllers.clone(),
+        )));
+        (self.spawner)(Box::pin(pipe(
+            server_read,
+            client_write,
+            self.server_to_client.clone(),
+            controllers,
+        )));
+
+        Ok(())
+    }
+}
+
+async fn pipe(
+    mut from: ReadHalf,
+    mut to: WriteHalf,
+    interceptor: SharedInterceptor,
+    controllers: [Controller; 2],
+) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match from.receive(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(len) => len,
+        };
+
+        let action = interceptor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .intercept(buf[..len].to_vec());
+
+        let result = match action {
+            Action::Forward(bytes) => to.send(&bytes),
+            Action::Delay(delay, bytes) => {
+                sleep(delay).await;
+                to.send(&bytes)
+            }
+            Action::Drop => Ok(0),
+            Action::Reset => {
+                controllers.iter().for_each(Controller::reset);
+                return;
+            }
+        };
+
+        if result.is_err() {
+            return;
+        }
+    }
+}
+
+/// Waits for given duration without depending on any runtime (the timer runs on a separate thread)
+pub(crate) async fn sleep(duration: Duration) {
+    let (sender, receiver) = bounded(1);
+
+    thread::spawn(move || {
+        thread::sleep(duration);
+        let _ = sender.try_send(());
+    });
+
+    let _ = receiver.recv().await;
+}
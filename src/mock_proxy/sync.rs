@@ -0,0 +1,189 @@
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+
+use crate::{
+    error::Error,
+    intercept::forward,
+    sync::{
+        Action, Controller, Handle, Interceptor, MockListener, MockStream, ReadHalf, WriteHalf,
+    },
+};
+
+/// Size of the buffer used for reading data flowing through the proxy
+const BUFFER_SIZE: usize = 8 * 1024;
+
+type SharedInterceptor = Arc<Mutex<Box<dyn Interceptor>>>;
+
+/// Synchronous mock proxy which sits between clients and a mock IO listener
+///
+/// Every connection accepted by the proxy is connected to the listener of given handle. Data flowing in each
+/// direction is passed through an interceptor which can observe, modify, delay or drop it or reset the connection.
+///
+/// ```rust
+/// # use std::io::{Read, Write};
+/// use mock_io::sync::{Action, MockListener, MockProxy, MockStream};
+///
+/// let (proxy_listener, proxy_handle) = MockListener::new();
+/// let (listener, handle) = MockListener::new();
+///
+/// let proxy = MockProxy::new(proxy_listener, handle)
+///     .client_to_server(|bytes: Vec<u8>| Action::Forward(bytes.to_ascii_uppercase()));
+///
+/// let mut client = MockStream::connect(&proxy_handle).unwrap();
+/// proxy.accept().unwrap();
+/// let mut server = listener.accept().unwrap();
+///
+/// client.write_all(b"hello").unwrap();
+///
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"HELLO", &buf);
+/// ```
+pub struct MockProxy {
+    listener: MockListener,
+    handle: Handle,
+    client_to_server: SharedInterceptor,
+    server_to_client: SharedInterceptor,
+}
+
+impl MockProxy {
+    /// Creates a new proxy which accepts connections from `listener` and connects them to the listener of `handle`
+    ///
+    /// All the data is forwarded as is until interceptors are set.
+    pub fn new(listener: MockListener, handle: Handle) -> Self {
+        Self {
+            listener,
+            handle,
+            client_to_server: Arc::new(Mutex::new(Box::new(forward))),
+            server_to_client: Arc::new(Mutex::new(Box::new(forward))),
+        }
+    }
+
+    /// Sets the interceptor for data flowing from clients to the server
+    pub fn client_to_server(mut self, interceptor: impl Interceptor) -> Self {
+        self.client_to_server = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Sets the interceptor for data flowing from the server to clients
+    pub fn server_to_client(mut self, interceptor: impl Interceptor) -> Self {
+        self.server_to_client = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Accepts one connection, connects it to the server and forwards data between them on background threads
+    pub fn accept(&self) -> Result<(), Error> {
+        let client = self.listener.accept()?;
+        self.forward(client)
+    }
+
+    /// Accepts connections until all the handles of the proxy's listener are dropped
+    pub fn run(self) -> Result<(), Error> {
+        while let Ok(client) = self.listener.accept() {
+            self.forward(client)?;
+        }
+
+        Ok(())
+    }
+
+    fn forward(&self, client: MockStream) -> Result<(), Error> {
+        let server = MockStream::connect(&self.handle)?;
+
+        let controllers = [client.controller(), server.controller()];
+
+        let (client_read, client_write) = client.split();
+        let (server_read, server_write) = server.split();
+
+        let interceptor = self.client_to_server.clone();
+        let controllers_clone = controllers.clone();
+        thread::spawn(move || pipe(client_read, server_write, interceptor, controllers_clone));
+
+        let interceptor = self.server_to_client.clone();
+        thread::spawn(move || pipe(server_read, client_write, interceptor, controllers));
+
+        Ok(())
+    }
+}
+
+fn pipe(
+    mut from: ReadHalf,
+    mut to: WriteHalf,
+    interceptor: SharedInterceptor,
+    controllers: [Controller; 2],
+) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match from.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(len) => len,
+        };
+
+        let action = interceptor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .intercept(buf[..len].to_vec());
+
+        let result = match action {
+            Action::Forward(bytes) => to.write_all(&bytes),
+            Action::Delay(delay, bytes) => {
+                thread::sleep(delay);
+                to.write_all(&bytes)
+            }
+            Action::Drop => Ok(()),
+            Action::Reset => {
+                controllers.iter().for_each(Controller::reset);
+                return;
+            }
+        };
+
+        if result.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    #[test]
+    fn check_interceptors() {
+        let (proxy_listener, proxy_handle) = MockListener::new();
+        let (listener, handle) = MockListener::new();
+
+        let proxy = MockProxy::new(proxy_listener, handle).server_to_client(|bytes: Vec<u8>| {
+            match bytes.as_slice() {
+                b"drop" => Action::Drop,
+                b"reset" => Action::Reset,
+                _ => Action::Forward(bytes),
+            }
+        });
+
+        let mut client = MockStream::connect(&proxy_handle).unwrap();
+        proxy.accept().unwrap();
+        let mut server = listener.accept().unwrap();
+
+        let mut buf = [0; 2];
+
+        client.write_all(b"hi").unwrap();
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(b"hi", &buf);
+
+        server.write_all(b"drop").unwrap();
+        server.write_all(b"ok").unwrap();
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(b"ok", &buf);
+
+        server.write_all(b"reset").unwrap();
+        assert_eq!(
+            ErrorKind::ConnectionReset,
+            client.read(&mut buf).unwrap_err().kind()
+        );
+    }
+}
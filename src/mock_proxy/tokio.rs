@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::{io::ReadBuf, time};
+
+use crate::{
+    error::Error,
+    intercept::forward,
+    tokio::{
+        Action, Controller, Handle, Interceptor, MockListener, MockStream, ReadHalf, WriteHalf,
+    },
+};
+
+/// Size of the buffer used for reading data flowing through the proxy
+const BUFFER_SIZE: usize = 8 * 1024;
+
+type SharedInterceptor = Arc<Mutex<Box<dyn Interceptor>>>;
+
+/// Asynchronous mock proxy which sits between clients and a mock IO listener
+///
+/// Every connection accepted by the proxy is connected to the listener of given handle. Data flowing in each
+/// direction is passed through an interceptor which can observe, modify, delay or drop it or reset the connection.
+/// Forwarding tasks are spawned on the current tokio runtime.
+pub struct MockProxy {
+    listener: MockListener,
+    handle: Handle,
+    client_to_server: SharedInterceptor,
+    server_to_client: SharedInterceptor,
+}
+
+impl MockProxy {
+    /// Creates a new proxy which accepts connections from `listener` and connects them to the listener of `handle`
+    ///
+    /// All the data is forwarded as is until interceptors are set.
+    pub fn new(listener: MockListener, handle: Handle) -> Self {
+        Self {
+            listener,
+            handle,
+            client_to_server: Arc::new(Mutex::new(Box::new(forward))),
+            server_to_client: Arc::new(Mutex::new(Box::new(forward))),
+        }
+    }
+
+    /// Sets the interceptor for data flowing from clients to the server
+    pub fn client_to_server(mut self, interceptor: impl Interceptor) -> Self {
+        self.client_to_server = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Sets the interceptor for data flowing from the server to clients
+    pub fn server_to_client(mut self, interceptor: impl Interceptor) -> Self {
+        self.server_to_client = Arc::new(Mutex::new(Box::new(interceptor)));
+        self
+    }
+
+    /// Accepts one connection, connects it to the server and spawns tasks forwarding data between them
+    pub async fn accept(&mut self) -> Result<(), Error> {
+        let client = self.listener.accept().await?;
+        self.forward(client)
+    }
+
+    /// Accepts connections until all the handles of the proxy's listener are dropped
+    pub async fn run(mut self) -> Result<(), Error> {
+        while let Ok(client) = self.listener.accept().await {
+            self.forward(client)?;
+        }
+
+        Ok(())
+    }
+
+    fn forward(&self, client: MockStream) -> Result<(), Error> {
+        let server = MockStream::connect(&self.handle)?;
+
+        let controllers = [client.controller(), server.controller()];
+
+        let (client_read, client_write) = client.split();
+        let (server_read, server_write) = server.split();
+
+        tokio::spawn(pipe(
+            client_read,
+            server_write,
+            self.client_to_server.clone(),
+            controllers.clone(),
+        ));
+        tokio::spawn(pipe(
+            server_read,
+            client_write,
+            self.server_to_client.clone(),
+            controllers,
+        ));
+
+        Ok(())
+    }
+}
+
+async fn pipe(
+    mut from: ReadHalf,
+    to: WriteHalf,
+    interceptor: SharedInterceptor,
+    controllers: [Controller; 2],
+) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let mut read_buf = ReadBuf::new(&mut buf);
+
+        if from.receive(&mut read_buf).await.is_err() || read_buf.filled().is_empty() {
+            return;
+        }
+
+        let action = interceptor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .intercept(read_buf.filled().to_vec());
+
+        let result = match action {
+            Action::Forward(bytes) => to.send(&bytes),
+            Action::Delay(delay, bytes) => {
+                time::sleep(delay).await;
+                to.send(&bytes)
+            }
+            Action::Drop => Ok(0),
+            Action::Reset => {
+                controllers.iter().for_each(Controller::reset);
+                return;
+            }
+        };
+
+        if result.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test(start_paused = true)]
+    async fn check_interceptors() {
+        let (proxy_listener, proxy_handle) = MockListener::new();
+        let (mut listener, handle) = MockListener::new();
+
+        let mut proxy =
+            MockProxy::new(proxy_listener, handle).client_to_server(|bytes: Vec<u8>| {
+                Action::Delay(Duration::from_secs(1), bytes.to_ascii_uppercase())
+            });
+
+        let mut client = MockStream::connect(&proxy_handle).unwrap();
+        proxy.accept().await.unwrap();
+        let mut server = listener.accept().await.unwrap();
+
+        let start = time::Instant::now();
+        client.write_all(b"hello").await.unwrap();
+
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"HELLO", &buf);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        server.write_all(b"bye").await.unwrap();
+        let mut buf = [0; 3];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"bye", &buf);
+    }
+}
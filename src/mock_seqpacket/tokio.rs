@@ -0,0 +1,79 @@
+use crate::{
+    error::Error,
+    tokio::{Controller, MockStream},
+};
+
+/// Asynchronous message-preserving mock IO stream (emulates `SOCK_SEQPACKET` sockets)
+///
+/// Every call to `send_msg` sends exactly one message and every call to `recv_msg` receives exactly one message.
+#[derive(Debug)]
+pub struct MockSeqPacket {
+    stream: MockStream,
+}
+
+impl MockSeqPacket {
+    /// Creates a pair of connected message-preserving mock streams
+    pub fn pair() -> (Self, Self) {
+        let (stream_1, stream_2) = MockStream::pair();
+        (Self { stream: stream_1 }, Self { stream: stream_2 })
+    }
+
+    /// Sends a message to the peer and returns its length
+    pub async fn send_msg(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        self.stream.send_msg(msg)
+    }
+
+    /// Receives one message from the peer
+    ///
+    /// Returns the number of bytes copied into the buffer along with a flag which is `true` if the message was
+    /// truncated because the buffer is too small (rest of a truncated message is discarded), or `None` once the peer
+    /// is closed. Empty messages are received as `Some((0, false))`.
+    pub async fn recv_msg(&mut self, buf: &mut [u8]) -> Result<Option<(usize, bool)>, Error> {
+        self.stream.receive_msg(buf).await
+    }
+
+    /// Returns a controller for simulating network partitions and resets on the connection of this stream
+    pub fn controller(&self) -> Controller {
+        self.stream.controller()
+    }
+
+    /// Returns the underlying mock stream (can be used for configuring socket options)
+    pub fn get_ref(&self) -> &MockStream {
+        &self.stream
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn check_message_boundaries() {
+        let (mut client, mut server) = MockSeqPacket::pair();
+
+        assert!(matches!(client.send_msg(&[1, 2, 3]).await, Ok(3)));
+        assert!(matches!(client.send_msg(&[]).await, Ok(0)));
+        assert!(matches!(client.send_msg(&[4, 5]).await, Ok(2)));
+
+        let mut buf = [0; 2];
+        assert!(matches!(
+            server.recv_msg(&mut buf).await,
+            Ok(Some((2, true)))
+        ));
+        assert_eq!([1, 2], buf);
+
+        let mut buf = [0; 8];
+        assert!(matches!(
+            server.recv_msg(&mut buf).await,
+            Ok(Some((0, false)))
+        ));
+        assert!(matches!(
+            server.recv_msg(&mut buf).await,
+            Ok(Some((2, false)))
+        ));
+        assert_eq!([4, 5], buf[..2]);
+
+        drop(client);
+        assert!(matches!(server.recv_msg(&mut buf).await, Ok(None)));
+    }
+}
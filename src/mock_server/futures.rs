@@ -0,0 +1,195 @@
+use std::{future::Future, sync::Arc};
+
+use async_channel::bounded;
+
+use crate::{
+    error::Error,
+    futures::{MockListener, MockStream},
+    mock_proxy::futures::sleep,
+    server::{Codec, Reply, Rules},
+    spawn::{Spawner, Task},
+};
+
+/// Size of the buffer used for reading requests
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Asynchronous mock server which answers decoded requests according to a set of rules
+///
+/// Every request received by the server is decoded with its codec, recorded and answered with the reply of the first
+/// rule whose request is equal to it. Requests which do not match any rule are recorded but not answered. Tasks
+/// accepting and serving connections are spawned using the spawner supplied to the server.
+pub struct MockServer<C: Codec> {
+    codec: C,
+    rules: Rules<C>,
+    spawner: Spawner,
+}
+
+impl<C: Codec> MockServer<C> {
+    /// Creates a new mock server which decodes requests and encodes responses with given codec
+    ///
+    /// The spawner has the same signature as the one taken by the spawning helpers (e.g., `spawn_echo`), so the same
+    /// closure can be used for both.
+    pub fn new(codec: C, spawner: impl Fn(Task) + Send + Sync + 'static) -> Self {
+        Self {
+            codec,
+            rules: Rules::new(),
+            spawner: Arc::new(spawner),
+        }
+    }
+
+    /// Adds a rule which answers requests equal to `request` with `reply`
+    pub fn rule(self, request: C::Request, reply: Reply<C::Response>) -> Self {
+        self.rules.push(request, reply);
+        self
+    }
+
+    /// Accepts connections from given listener on a background task (until all the handles of the listener are
+    /// dropped) and serves each of them on its own task
+    ///
+    /// The returned future completes once the listener stops accepting and every accepted connection is closed, so
+    /// awaiting it before calling `verify` makes sure that all the requests sent to the server are recorded. The server
+    /// runs whether or not the future is polled.
+    pub fn serve(&self, listener: MockListener) -> impl Future<Output = ()> + Send + 'static {
+        let codec = self.codec.clone();
+        let rules = self.rules.clone();
+        let spawner = self.spawner.clone();
+
+        // Every task holds a sender, so receiving fails once all of them are done
+        let (running, finished) = bounded::<()>(1);
+
+        (self.spawner)(Box::pin(async move {
+            while let Ok(stream) = listener.accept().await {
+                let running = running.clone();
+                let connection = serve_connection(stream, codec.clone(), rules.clone());
+
+                spawner(Box::pin(async move {
+                    connection.await;
+                    drop(running);
+                }));
+            }
+        }));
+
+        async move {
+            let _ = finished.recv().await;
+        }
+    }
+
+    /// Returns all the requests received by the server so far
+    pub fn requests(&self) -> Vec<C::Request> {
+        self.rules.requests()
+    }
+
+    /// Returns an error if the request of any rule never arrived
+    pub fn verify(&self) -> Result<(), Error> {
+        self.rules.verify()
+    }
+}
+
+async fn serve_connection<C: Codec>(mut stream: MockStream, mut codec: C, rules: Rules<C>) {
+    let mut received = Vec::new();
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match stream.receive(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(len) => len,
+        };
+
+        received.extend_from_slice(&buf[..len]);
+
+        loop {
+            let request = match codec.decode(&mut received) {
+                Ok(Some(request)) => request,
+                Ok(None) => break,
+                Err(_) => return,
+            };
+
+            let response = match rules.reply(request) {
+                None => continue,
+                Some(Reply::Respond(response)) => response,
+                Some(Reply::Delay(delay, response)) => {
+                    sleep(delay).await;
+                    response
+                }
+                Some(Reply::Close) => return,
+                Some(Reply::Reset) => {
+                    stream.controller().reset();
+                    return;
+                }
+            };
+
+            let mut bytes = Vec::new();
+
+            if codec.encode(response, &mut bytes).is_err() || stream.send(&bytes).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::futures::{spawn_echo, LineCodec};
+
+    #[test]
+    fn check_server_rules() {
+        async_std::task::block_on(async {
+            let (listener, handle) = MockListener::new();
+
+            let server = MockServer::new(LineCodec, |task| {
+                async_std::task::spawn(task);
+            })
+            .rule("ping".to_owned(), Reply::Respond("pong".to_owned()))
+            .rule("quit".to_owned(), Reply::Close);
+            let serving = server.serve(listener);
+
+            let mut client = MockStream::connect(&handle).await.unwrap();
+            client.write_all(b"ping\nquit\n").await.unwrap();
+
+            let mut buf = Vec::new();
+            client.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(b"pong\n", &buf[..]);
+
+            drop(handle);
+            serving.await;
+
+            assert_eq!(vec!["ping", "quit"], server.requests());
+            server.verify().unwrap();
+        });
+    }
+
+    #[test]
+    fn check_shared_spawner() {
+        async_std::task::block_on(async {
+            let spawner = |task| {
+                async_std::task::spawn(task);
+            };
+
+            let (mut client, server) = MockStream::pair();
+            spawn_echo(&spawner, server);
+
+            let (listener, handle) = MockListener::new();
+            let server = MockServer::new(LineCodec, spawner)
+                .rule("ping".to_owned(), Reply::Respond("pong".to_owned()));
+            let serving = server.serve(listener);
+
+            client.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            client.read_exact(&mut buf).await.unwrap();
+            assert_eq!(b"hello", &buf);
+
+            let mut client = MockStream::connect(&handle).await.unwrap();
+            client.write_all(b"ping\n").await.unwrap();
+            client.read_exact(&mut buf).await.unwrap();
+            assert_eq!(b"pong\n", &buf);
+
+            drop((client, handle));
+            serving.await;
+            server.verify().unwrap();
+        });
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    io::{Read, Write},
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    error::Error,
+    server::{Codec, Reply, Rules},
+    sync::{MockListener, MockStream},
+};
+
+/// Size of the buffer used for reading requests
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Synchronous mock server which answers decoded requests according to a set of rules
+///
+/// Every request received by the server is decoded with its codec, recorded and answered with the reply of the first
+/// rule whose request is equal to it. Requests which do not match any rule are recorded but not answered.
+///
+/// ```rust
+/// # use std::io::{Read, Write};
+/// use mock_io::sync::{LineCodec, MockListener, MockServer, MockStream, Reply};
+///
+/// let (listener, handle) = MockListener::new();
+///
+/// let server = MockServer::new(LineCodec).rule("ping".to_owned(), Reply::Respond("pong".to_owned()));
+/// let serving = server.serve(listener);
+///
+/// let mut client = MockStream::connect(&handle).unwrap();
+/// client.write_all(b"ping\n").unwrap();
+///
+/// let mut buf = [0; 5];
+/// client.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"pong\n", &buf);
+///
+/// drop((client, handle));
+/// serving.join().unwrap();
+///
+/// assert_eq!(vec!["ping".to_owned()], server.requests());
+/// server.verify().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct MockServer<C: Codec> {
+    codec: C,
+    rules: Rules<C>,
+}
+
+impl<C: Codec> MockServer<C> {
+    /// Creates a new mock server which decodes requests and encodes responses with given codec
+    pub fn new(codec: C) -> Self {
+        Self {
+            codec,
+            rules: Rules::new(),
+        }
+    }
+
+    /// Adds a rule which answers requests equal to `request` with `reply`
+    pub fn rule(self, request: C::Request, reply: Reply<C::Response>) -> Self {
+        self.rules.push(request, reply);
+        self
+    }
+
+    /// Accepts connections from given listener on a background thread (until all the handles of the listener are
+    /// dropped) and serves each of them on its own thread
+    ///
+    /// The returned thread finishes once the listener stops accepting and every accepted connection is closed, so
+    /// joining it before calling `verify` makes sure that all the requests sent to the server are recorded.
+    pub fn serve(&self, listener: MockListener) -> JoinHandle<()> {
+        let codec = self.codec.clone();
+        let rules = self.rules.clone();
+
+        thread::spawn(move || {
+            let mut connections = Vec::new();
+
+            while let Ok(stream) = listener.accept() {
+                let codec = codec.clone();
+                let rules = rules.clone();
+                connections.push(thread::spawn(move || {
+                    serve_connection(stream, codec, rules)
+                }));
+            }
+
+            for connection in connections {
+                let _ = connection.join();
+            }
+        })
+    }
+
+    /// Returns all the requests received by the server so far
+    pub fn requests(&self) -> Vec<C::Request> {
+        self.rules.requests()
+    }
+
+    /// Returns an error if the request of any rule never arrived
+    pub fn verify(&self) -> Result<(), Error> {
+        self.rules.verify()
+    }
+}
+
+fn serve_connection<C: Codec>(mut stream: MockStream, mut codec: C, rules: Rules<C>) {
+    let mut received = Vec::new();
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(len) => len,
+        };
+
+        received.extend_from_slice(&buf[..len]);
+
+        loop {
+            let request = match codec.decode(&mut received) {
+                Ok(Some(request)) => request,
+                Ok(None) => break,
+                Err(_) => return,
+            };
+
+            let response = match rules.reply(request) {
+                None => continue,
+                Some(Reply::Respond(response)) => response,
+                Some(Reply::Delay(delay, response)) => {
+                    thread::sleep(delay);
+                    response
+                }
+                Some(Reply::Close) => return,
+                Some(Reply::Reset) => {
+                    stream.controller().reset();
+                    return;
+                }
+            };
+
+            let mut bytes = Vec::new();
+
+            if codec.encode(response, &mut bytes).is_err() || stream.write_all(&bytes).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::ErrorKind;
+
+    use crate::sync::LineCodec;
+
+    #[test]
+    fn check_server_rules() {
+        let (listener, handle) = MockListener::new();
+
+        let server = MockServer::new(LineCodec)
+            .rule("ping".to_owned(), Reply::Respond("pong".to_owned()))
+            .rule("reset".to_owned(), Reply::Reset)
+            .rule("never".to_owned(), Reply::Close);
+        let serving = server.serve(listener);
+
+        let mut client = MockStream::connect(&handle).unwrap();
+        client.write_all(b"unknown\r\nping\n").unwrap();
+
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(b"pong\n", &buf);
+
+        client.write_all(b"reset\n").unwrap();
+        assert_eq!(
+            ErrorKind::ConnectionReset,
+            client.read(&mut buf).unwrap_err().kind()
+        );
+
+        drop(handle);
+        serving.join().unwrap();
+
+        assert_eq!(vec!["unknown", "ping", "reset"], server.requests());
+
+        let error = server.verify().unwrap_err();
+        assert_eq!(crate::error::ErrorKind::MissingRequests, error.kind());
+        assert!(format!("{:?}", error).contains("never"));
+    }
+}
@@ -0,0 +1,152 @@
+use tokio::{task::JoinHandle, time};
+
+use crate::{
+    error::Error,
+    server::{Codec, Reply, Rules},
+    tokio::{MockListener, MockStream},
+};
+
+/// Size of the buffer used for reading requests
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Asynchronous mock server which answers decoded requests according to a set of rules
+///
+/// Every request received by the server is decoded with its codec, recorded and answered with the reply of the first
+/// rule whose request is equal to it. Requests which do not match any rule are recorded but not answered. Connections
+/// are served by tasks spawned on the current tokio runtime.
+#[derive(Debug)]
+pub struct MockServer<C: Codec> {
+    codec: C,
+    rules: Rules<C>,
+}
+
+impl<C: Codec> MockServer<C> {
+    /// Creates a new mock server which decodes requests and encodes responses with given codec
+    pub fn new(codec: C) -> Self {
+        Self {
+            codec,
+            rules: Rules::new(),
+        }
+    }
+
+    /// Adds a rule which answers requests equal to `request` with `reply`
+    pub fn rule(self, request: C::Request, reply: Reply<C::Response>) -> Self {
+        self.rules.push(request, reply);
+        self
+    }
+
+    /// Accepts connections from given listener on a background task (until all the handles of the listener are
+    /// dropped) and serves each of them on its own task
+    ///
+    /// The returned task finishes once the listener stops accepting and every accepted connection is closed, so
+    /// awaiting it before calling `verify` makes sure that all the requests sent to the server are recorded.
+    pub fn serve(&self, mut listener: MockListener) -> JoinHandle<()> {
+        let codec = self.codec.clone();
+        let rules = self.rules.clone();
+
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+
+            while let Ok(stream) = listener.accept().await {
+                connections.push(tokio::spawn(serve_connection(
+                    stream,
+                    codec.clone(),
+                    rules.clone(),
+                )));
+            }
+
+            for connection in connections {
+                let _ = connection.await;
+            }
+        })
+    }
+
+    /// Returns all the requests received by the server so far
+    pub fn requests(&self) -> Vec<C::Request> {
+        self.rules.requests()
+    }
+
+    /// Returns an error if the request of any rule never arrived
+    pub fn verify(&self) -> Result<(), Error> {
+        self.rules.verify()
+    }
+}
+
+async fn serve_connection<C: Codec>(mut stream: MockStream, mut codec: C, rules: Rules<C>) {
+    let mut received = Vec::new();
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let len = match stream.receive(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(len) => len,
+        };
+
+        received.extend_from_slice(&buf[..len]);
+
+        loop {
+            let request = match codec.decode(&mut received) {
+                Ok(Some(request)) => request,
+                Ok(None) => break,
+                Err(_) => return,
+            };
+
+            let response = match rules.reply(request) {
+                None => continue,
+                Some(Reply::Respond(response)) => response,
+                Some(Reply::Delay(delay, response)) => {
+                    time::sleep(delay).await;
+                    response
+                }
+                Some(Reply::Close) => return,
+                Some(Reply::Reset) => {
+                    stream.controller().reset();
+                    return;
+                }
+            };
+
+            let mut bytes = Vec::new();
+
+            if codec.encode(response, &mut bytes).is_err() || stream.send(&bytes).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::tokio::LineCodec;
+
+    #[tokio::test(start_paused = true)]
+    async fn check_server_rules() {
+        let (listener, handle) = MockListener::new();
+
+        let server = MockServer::new(LineCodec)
+            .rule(
+                "ping".to_owned(),
+                Reply::Delay(Duration::from_secs(1), "pong".to_owned()),
+            )
+            .rule("quit".to_owned(), Reply::Close);
+        let serving = server.serve(listener);
+
+        let mut client = MockStream::connect(&handle).unwrap();
+        client.write_all(b"ping\nquit\n").await.unwrap();
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(b"pong\n", &buf[..]);
+
+        drop(handle);
+        serving.await.unwrap();
+
+        assert_eq!(vec!["ping", "quit"], server.requests());
+        server.verify().unwrap();
+    }
+}
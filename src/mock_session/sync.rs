@@ -0,0 +1,173 @@
+use std::io::{ErrorKind, Read, Write};
+
+use crate::{
+    error::Error,
+    script::Fault,
+    session::{Session, SessionStep},
+    sync::{Direction, MockStream, ReadHalf},
+};
+
+/// Size of the buffer used for draining data written by the stream under test
+const BUFFER_SIZE: usize = 8 * 1024;
+
+impl MockStream {
+    /// Creates a mock stream whose peer plays given session. Returns the stream under test along with the peer which
+    /// collects the data written by the stream.
+    pub fn from_session(session: Session) -> (Self, SessionPeer) {
+        let (stream, peer) = Self::pair();
+        let controller = stream.controller();
+        let (read_half, mut write_half) = peer.split();
+
+        let mut expected = Vec::new();
+        let mut closed = false;
+
+        for step in session.into_steps() {
+            match step {
+                SessionStep::Send(bytes) if !closed => {
+                    let _ = write_half.write(&bytes);
+                }
+                SessionStep::Send(_) => {}
+                SessionStep::Expect(bytes) => expected.extend(bytes),
+                SessionStep::Fault(Fault::Reset) => {
+                    let _ = write_half.abort();
+                    break;
+                }
+                SessionStep::Fault(Fault::Blackhole) => controller.blackhole(Direction::Incoming),
+                SessionStep::Close => closed = true,
+            }
+        }
+
+        let peer = SessionPeer {
+            read_half,
+            written: Default::default(),
+            expected,
+        };
+
+        (stream, peer)
+    }
+
+    /// Creates a mock stream whose peer plays the session described by given fuzz input. The same input always
+    /// produces the same reads, so a crashing input can be replayed in a unit test.
+    ///
+    /// ```rust
+    /// # use std::io::Read;
+    /// use mock_io::sync::MockStream;
+    ///
+    /// let input = b"\x00\x03abc";
+    ///
+    /// let (mut stream, _peer) = MockStream::from_fuzz(input);
+    /// let mut buf = Vec::new();
+    /// let _ = stream.read_to_end(&mut buf);
+    ///
+    /// let (mut replay, _peer) = MockStream::from_fuzz(input);
+    /// let mut replayed = Vec::new();
+    /// let _ = replay.read_to_end(&mut replayed);
+    ///
+    /// assert_eq!(buf, replayed);
+    /// ```
+    #[cfg(feature = "arbitrary")]
+    pub fn from_fuzz(data: &[u8]) -> (Self, SessionPeer) {
+        Self::from_session(Session::from_fuzz(data))
+    }
+}
+
+/// Peer of a mock stream which plays a session
+#[derive(Debug)]
+pub struct SessionPeer {
+    read_half: ReadHalf,
+    written: Vec<u8>,
+    expected: Vec<u8>,
+}
+
+impl SessionPeer {
+    /// Returns all the data written by the stream under test so far (without blocking)
+    pub fn written(&mut self) -> Result<&[u8], Error> {
+        self.read_half.set_nonblocking(true)?;
+        let mut buf = [0; BUFFER_SIZE];
+
+        loop {
+            match self.read_half.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => self.written.extend_from_slice(&buf[..len]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::ConnectionReset => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        Ok(&self.written)
+    }
+
+    /// Returns the data the stream under test is expected to write (i.e., the bytes of all the `Expect` steps)
+    pub fn expected(&self) -> &[u8] {
+        &self.expected
+    }
+
+    /// Returns `true` if the data written by the stream under test so far matches the expected data
+    pub fn check(&mut self) -> bool {
+        let expected = std::mem::take(&mut self.expected);
+        let matches = matches!(self.written(), Ok(written) if written == &expected[..]);
+        self.expected = expected;
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_session_playback() {
+        let session = Session::new()
+            .step(SessionStep::Send(b"hello".to_vec()))
+            .step(SessionStep::Expect(b"world".to_vec()))
+            .step(SessionStep::Fault(Fault::Blackhole))
+            .step(SessionStep::Send(b"dropped".to_vec()))
+            .step(SessionStep::Close);
+
+        let (mut stream, mut peer) = MockStream::from_session(session);
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        assert_eq!(b"hello", &buf[..]);
+
+        assert!(!peer.check());
+        stream.write_all(b"world").unwrap();
+        assert!(peer.check());
+
+        let session = Session::new()
+            .step(SessionStep::Send(b"hello".to_vec()))
+            .step(SessionStep::Fault(Fault::Reset))
+            .step(SessionStep::Send(b"world".to_vec()));
+
+        let (mut stream, _peer) = MockStream::from_session(session);
+
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(b"hello", &buf);
+        assert_eq!(
+            ErrorKind::ConnectionReset,
+            stream.read(&mut buf).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::ConnectionReset,
+            stream.write(b"world").unwrap_err().kind()
+        );
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn check_fuzz_replay() {
+        let input = b"\x00\x05hello\x06\x01\x00\x03abc";
+        assert_eq!(Session::from_fuzz(input), Session::from_fuzz(input));
+
+        let reads = |input: &[u8]| {
+            let (mut stream, _peer) = MockStream::from_fuzz(input);
+            let mut buf = Vec::new();
+            let result = stream.read_to_end(&mut buf).map_err(|error| error.kind());
+            (buf, result)
+        };
+
+        assert_eq!(reads(input), reads(input));
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "embedded")]
+pub mod embedded;
 #[cfg(feature = "async-futures")]
 pub mod futures;
 #[cfg(feature = "sync")]
@@ -0,0 +1,219 @@
+use core::{fmt, future::poll_fn, hint};
+
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+
+use crate::pipe::{Pipe, PipeError};
+
+/// Mock IO stream implementing `embedded-io` and `embedded-io-async` traits (usable in `no_std` + `alloc`)
+///
+/// Writes never block. Blocking reads spin until data is written by the peer (or the peer is dropped), so drivers
+/// running on a single thread should check `read_ready` first or use the async traits instead.
+///
+/// ```rust
+/// use embedded_io::{Read, Write};
+/// use mock_io::embedded::MockStream;
+///
+/// let (mut client, mut server) = MockStream::pair();
+/// client.write_all(b"hello").unwrap();
+/// drop(client);
+///
+/// let mut buf = [0; 8];
+/// assert_eq!(5, server.read(&mut buf).unwrap());
+/// assert_eq!(b"hello", &buf[..5]);
+/// assert_eq!(0, server.read(&mut buf).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct MockStream {
+    incoming: Pipe,
+    outgoing: Pipe,
+}
+
+impl MockStream {
+    /// Creates a pair of connected mock streams
+    pub fn pair() -> (Self, Self) {
+        let pipe_1 = Pipe::default();
+        let pipe_2 = Pipe::default();
+
+        let stream_1 = Self {
+            incoming: pipe_2.clone(),
+            outgoing: pipe_1.clone(),
+        };
+
+        let stream_2 = Self {
+            incoming: pipe_1,
+            outgoing: pipe_2,
+        };
+
+        (stream_1, stream_2)
+    }
+
+    /// Resets the connection. All subsequent reads and writes on both streams fail with `ConnectionReset` error and
+    /// any unread data is lost.
+    pub fn reset(&self) {
+        self.incoming.reset();
+        self.outgoing.reset();
+    }
+
+    /// Returns `true` if the connection has been reset
+    pub fn is_reset(&self) -> bool {
+        self.incoming.is_reset()
+    }
+}
+
+impl Drop for MockStream {
+    fn drop(&mut self) {
+        self.incoming.close();
+        self.outgoing.close();
+    }
+}
+
+impl ErrorType for MockStream {
+    type Error = Error;
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            if let Some(result) = self.incoming.try_read(buf) {
+                return result.map_err(Into::into);
+            }
+
+            hint::spin_loop();
+        }
+    }
+}
+
+impl ReadReady for MockStream {
+    fn read_ready(&mut self) -> Result<bool, Error> {
+        Ok(self.incoming.is_ready())
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.outgoing.write(buf).map_err(Into::into)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl WriteReady for MockStream {
+    fn write_ready(&mut self) -> Result<bool, Error> {
+        Ok(true)
+    }
+}
+
+impl embedded_io_async::Read for MockStream {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        poll_fn(|cx| self.incoming.poll_read(cx, buf))
+            .await
+            .map_err(Into::into)
+    }
+}
+
+impl embedded_io_async::Write for MockStream {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Error returned by embedded mock IO streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The peer has been dropped
+    BrokenPipe,
+    /// The connection has been reset
+    ConnectionReset,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BrokenPipe => write!(f, "Broken pipe"),
+            Self::ConnectionReset => write!(f, "Connection reset by peer"),
+        }
+    }
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::BrokenPipe => ErrorKind::BrokenPipe,
+            Self::ConnectionReset => ErrorKind::ConnectionReset,
+        }
+    }
+}
+
+impl From<PipeError> for Error {
+    fn from(error: PipeError) -> Self {
+        match error {
+            PipeError::Closed => Self::BrokenPipe,
+            PipeError::Reset => Self::ConnectionReset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn check_embedded_io() {
+        let (mut client, mut server) = MockStream::pair();
+        let mut buf = [0; 5];
+
+        assert!(!server.read_ready().unwrap());
+
+        let writer = thread::spawn(move || {
+            client.write_all(b"hello").unwrap();
+            client
+        });
+
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(b"hello", &buf);
+
+        let client = writer.join().unwrap();
+        client.reset();
+        assert_eq!(Err(Error::ConnectionReset), server.read(&mut buf));
+
+        let (client, mut server) = MockStream::pair();
+        drop(client);
+        assert_eq!(0, Read::read(&mut server, &mut buf).unwrap());
+        assert_eq!(Err(Error::BrokenPipe), Write::write(&mut server, b"hello"));
+    }
+
+    #[test]
+    fn check_embedded_io_async() {
+        let (mut client, mut server) = MockStream::pair();
+
+        let reader = thread::spawn(move || {
+            futures_executor::block_on(async {
+                let mut buf = [0; 5];
+                embedded_io_async::Read::read_exact(&mut server, &mut buf)
+                    .await
+                    .unwrap();
+                buf
+            })
+        });
+
+        futures_executor::block_on(async {
+            embedded_io_async::Write::write_all(&mut client, b"hel")
+                .await
+                .unwrap();
+            embedded_io_async::Write::write_all(&mut client, b"lo")
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(b"hello", &reader.join().unwrap());
+    }
+}
@@ -1,15 +1,31 @@
 use std::{
-    future::Future,
+    future::poll_fn,
     io,
+    net::SocketAddr,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use async_channel::{unbounded, Receiver, Sender};
+use futures_core::Stream;
 use futures_io::{AsyncRead, AsyncWrite};
 use pin_project_lite::pin_project;
 
-use crate::{error::Error, futures::Handle};
+use crate::{
+    controller::{self, check_reset, Deliver, Message, Wire},
+    datagram::copy_msg,
+    error::{Error, Inner},
+    flush::FlushBuffer,
+    futures::{Controller, FlushMode, Handle, Recording, Script, Simulation, SocketOptions, Stats},
+    lifecycle::Tracked,
+    primitives::atomic::AtomicBool,
+    recording::Recorder,
+    script::Shaping,
+    socket_options::{default_addrs, SharedOptions},
+    stats::StatsCell,
+};
 
 macro_rules! ready {
     ($e:expr $(,)?) => {
@@ -28,6 +44,9 @@ pin_project! {
         read_half: ReadHalf,
         #[pin]
         write_half: WriteHalf,
+        options: SharedOptions,
+        controller: Controller,
+        recorder: Recorder,
     }
 }
 
@@ -35,38 +54,245 @@ impl MockStream {
     /// Connects to a mock IO listener
     pub async fn connect(handle: &Handle) -> Result<Self, Error> {
         let (stream_1, stream_2) = Self::pair();
+
+        if let Some(simulation) = handle.simulation() {
+            stream_1.controller.simulate(simulation);
+        }
+
+        let tracked = Tracked {
+            lifecycle: handle.lifecycle().clone(),
+            id: handle.lifecycle().allocate(),
+        };
+        let id = tracked.id;
+
+        stream_1
+            .recorder
+            .track(tracked, stream_1.options.local_addr());
+        stream_2.read_half.stats.attach(handle.aggregate());
         handle.send(stream_2).await?;
+        handle.lifecycle().connect(id);
         Ok(stream_1)
     }
 
     /// Creates a pair of connected mock streams
     pub fn pair() -> (Self, Self) {
+        let (addr_1, addr_2) = default_addrs();
+        Self::pair_with_addrs(addr_1, addr_2)
+    }
+
+    /// Creates a pair of connected mock streams with given addresses (first stream is bound to `addr_1` and second
+    /// stream is bound to `addr_2`)
+    pub fn pair_with_addrs(addr_1: SocketAddr, addr_2: SocketAddr) -> (Self, Self) {
         let (sender_1, receiver_1) = unbounded();
         let (sender_2, receiver_2) = unbounded();
+        let (options_1, options_2) = SharedOptions::pair(addr_1, addr_2);
+        let (recorder, tap_1, tap_2) = Recorder::pair(addr_1, addr_2);
+        let (stats_1, stats_2) = StatsCell::pair();
+
+        let wire_1 = Arc::new(Wire::new(sender_2, tap_1, stats_1.clone()));
+        let wire_2 = Arc::new(Wire::new(sender_1, tap_2, stats_2.clone()));
+        let (controller_1, controller_2, reset) = controller::pair(&wire_1, &wire_2);
 
         let stream_1 = Self {
             read_half: ReadHalf {
                 receiver: receiver_1,
+                reset: reset.clone(),
                 remaining: Default::default(),
+                stats: stats_1.clone(),
             },
-            write_half: WriteHalf { sender: sender_2 },
+            write_half: WriteHalf {
+                wire: wire_1,
+                reset: reset.clone(),
+                stats: stats_1,
+                buffer: FlushBuffer::new(controller_1.unflushed()),
+                shaping: None,
+            },
+            options: options_1,
+            controller: controller_1,
+            recorder: recorder.clone(),
         };
 
         let stream_2 = Self {
             read_half: ReadHalf {
                 receiver: receiver_2,
+                reset: reset.clone(),
                 remaining: Default::default(),
+                stats: stats_2.clone(),
+            },
+            write_half: WriteHalf {
+                wire: wire_2,
+                reset,
+                stats: stats_2,
+                buffer: FlushBuffer::new(controller_2.unflushed()),
+                shaping: None,
             },
-            write_half: WriteHalf { sender: sender_1 },
+            options: options_2,
+            controller: controller_2,
+            recorder,
         };
 
         (stream_1, stream_2)
     }
 
+    /// Creates a pair of connected mock streams which follow given script (chunk policies and fault plans)
+    ///
+    /// Links are not supported by this backend, so an error of kind `InvalidInput` is returned if the script sets them.
+    pub fn pair_with_script(script: impl Into<Script>) -> Result<(Self, Self), Error> {
+        let script = script.into();
+
+        if script.has_links() {
+            return Err(Inner::UnsupportedLinks.into());
+        }
+
+        let (mut stream_1, mut stream_2) = Self::pair();
+        let (shaping_1, shaping_2) = script.shaping(&stream_1.controller, &stream_2.controller);
+        stream_1.write_half.shaping = Some(Arc::new(shaping_1));
+        stream_2.write_half.shaping = Some(Arc::new(shaping_2));
+
+        Ok((stream_1, stream_2))
+    }
+
+    /// Creates a pair of connected mock streams whose deliveries (in both directions) are scheduled by given
+    /// simulation
+    pub fn pair_with_simulation(simulation: &Simulation) -> (Self, Self) {
+        let (stream_1, stream_2) = Self::pair();
+        stream_1.controller.simulate(simulation);
+        (stream_1, stream_2)
+    }
+
     /// Splits the stream into separate read and write halves
     pub fn split(self) -> (ReadHalf, WriteHalf) {
         (self.read_half, self.write_half)
     }
+
+    pub(crate) fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send(bytes)
+    }
+
+    pub(crate) fn send_msg(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send_msg(bytes)
+    }
+
+    pub(crate) async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.receive(buf).await
+    }
+
+    pub(crate) async fn receive_msg(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, bool)>, Error> {
+        self.read_half.receive_msg(buf).await
+    }
+
+    /// Returns the address of this stream
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.local_addr())
+    }
+
+    /// Returns the address of the peer of this stream
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.peer_addr())
+    }
+
+    /// Starts recording the traffic on the connection of this stream (in both directions)
+    ///
+    /// Recording stops when the returned recording is dropped. This stream is treated as the client of the connection
+    /// when the recording is exported.
+    pub fn record(&self) -> Recording {
+        self.recorder
+            .start(self.options.local_addr(), self.options.peer_addr())
+    }
+
+    /// Returns a snapshot of the statistics of this stream
+    pub fn stats(&self) -> Stats {
+        self.read_half.stats()
+    }
+
+    /// Returns the ID of the connection of this stream in the lifecycle log of the listener it went through (if any)
+    pub(crate) fn connection_id(&self) -> Option<u64> {
+        self.recorder.connection_id()
+    }
+
+    /// Returns the number of bytes written by this stream which are not yet read by the peer
+    pub(crate) fn pending(&self) -> u64 {
+        self.read_half.stats.pending()
+    }
+
+    /// Sets the flush mode of this stream (i.e., when written data is delivered to the peer)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.write_half.set_flush_mode(mode)
+    }
+
+    /// Returns the flush mode of this stream
+    pub fn flush_mode(&self) -> FlushMode {
+        self.write_half.flush_mode()
+    }
+
+    /// Returns a controller for simulating network partitions and resets on the connection of this stream
+    pub fn controller(&self) -> Controller {
+        self.controller.clone()
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this stream
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<(), Error> {
+        self.options.update(|options| options.nodelay = nodelay);
+        Ok(())
+    }
+
+    /// Returns the value of the `TCP_NODELAY` option on this stream
+    pub fn nodelay(&self) -> Result<bool, Error> {
+        Ok(self.options.get().nodelay)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this stream
+    pub fn set_linger(&self, linger: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.linger = linger);
+        Ok(())
+    }
+
+    /// Returns the value of the `SO_LINGER` option on this stream
+    pub fn linger(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().linger)
+    }
+
+    /// Sets the value of the `IP_TTL` option on this stream
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), Error> {
+        self.options.update(|options| options.ttl = ttl);
+        Ok(())
+    }
+
+    /// Returns the value of the `IP_TTL` option on this stream
+    pub fn ttl(&self) -> Result<u32, Error> {
+        Ok(self.options.get().ttl)
+    }
+
+    /// Enables `SO_KEEPALIVE` with given idle time on this stream (`None` disables keepalive)
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.keepalive = keepalive);
+        Ok(())
+    }
+
+    /// Returns the keepalive idle time of this stream (`None` if keepalive is disabled)
+    pub fn keepalive(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().keepalive)
+    }
+
+    /// Returns a snapshot of all the socket options configured on this stream
+    pub fn options(&self) -> SocketOptions {
+        self.options.get()
+    }
+
+    /// Returns a snapshot of all the socket options configured on the peer of this stream
+    pub fn peer_options(&self) -> SocketOptions {
+        self.options.peer()
+    }
+
+    /// Receives data from the stream without removing it from the queue
+    ///
+    /// Successive calls return the same data. Waits until data is available.
+    pub async fn peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.peek(buf).await
+    }
 }
 
 impl AsyncRead for MockStream {
@@ -100,31 +326,95 @@ impl AsyncWrite for MockStream {
 /// Read half of asynchronous mock IO stream
 #[derive(Debug)]
 pub struct ReadHalf {
-    receiver: Receiver<Vec<u8>>,
+    receiver: Receiver<Message<Vec<u8>>>,
+    reset: Arc<AtomicBool>,
     remaining: Vec<u8>,
+    stats: StatsCell,
 }
 
 impl ReadHalf {
-    async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        let available_space = buf.len();
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Receives data from the read half without removing it from the queue
+    ///
+    /// Successive calls return the same data. Waits until data is available.
+    pub async fn peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.fill().await?;
+
+        let len = buf.len().min(self.remaining.len());
+        buf[..len].copy_from_slice(&self.remaining[..len]);
+
+        Ok(len)
+    }
+
+    /// Receives the next message if all the previous bytes have been read. Returns `false` (and remaining bytes stay
+    /// empty) if the peer has been dropped (i.e., on EOF).
+    async fn fill(&mut self) -> Result<bool, Error> {
+        poll_fn(|cx| self.poll_fill(cx)).await
+    }
+
+    /// Polls the next message if all the previous bytes have been read (the receiver keeps the task registered for
+    /// wake-ups between polls)
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, Error>> {
+        if let Err(error) = check_reset(&self.reset) {
+            return Poll::Ready(Err(error.into()));
+        }
 
         if self.remaining.is_empty() {
-            self.remaining = self.receiver.recv().await?;
+            match ready!(Pin::new(&mut self.receiver).poll_next(cx)) {
+                Some(Message::Data(bytes)) => {
+                    self.stats.message();
+                    self.remaining = bytes;
+                }
+                Some(Message::Reset) => return Poll::Ready(Err(Inner::ConnectionReset.into())),
+                None => return Poll::Ready(Ok(false)),
+            }
         }
 
+        Poll::Ready(Ok(true))
+    }
+
+    pub(crate) async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.fill().await?;
+        Ok(self.take(buf))
+    }
+
+    /// Moves the remaining bytes of the current message into the buffer (as many as fit)
+    fn take(&mut self, buf: &mut [u8]) -> usize {
+        let available_space = buf.len();
         let remaining_len = self.remaining.len();
 
-        if remaining_len > available_space {
+        let len = if remaining_len > available_space {
             buf.copy_from_slice(&self.remaining[..available_space]);
             self.remaining = self.remaining[available_space..].to_vec();
 
-            Ok(available_space)
+            available_space
         } else {
             buf[..remaining_len].copy_from_slice(&self.remaining);
             self.remaining = Default::default();
 
-            Ok(remaining_len)
+            remaining_len
+        };
+
+        self.stats.read(len, len);
+        len
+    }
+
+    /// Receives exactly one message (i.e., the bytes of one write) and returns its length along with a flag which is
+    /// `true` if the message was truncated to fit in the buffer (`None` on EOF)
+    async fn receive_msg(&mut self, buf: &mut [u8]) -> Result<Option<(usize, bool)>, Error> {
+        if !self.fill().await? {
+            return Ok(None);
         }
+
+        let message = std::mem::take(&mut self.remaining);
+        let (len, truncated) = copy_msg(&message, buf);
+        self.stats.read(len, message.len());
+
+        Ok(Some((len, truncated)))
     }
 }
 
@@ -134,8 +424,8 @@ impl AsyncRead for ReadHalf {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        let mut future = Box::pin(self.get_mut().receive(buf));
-        let result = ready!(future.as_mut().poll(cx));
+        let this = self.get_mut();
+        let result = ready!(this.poll_fill(cx)).map(|_| this.take(buf));
 
         Poll::Ready(result.map_err(Into::into))
     }
@@ -144,37 +434,164 @@ impl AsyncRead for ReadHalf {
 /// Write half of asynchronous mock IO stream
 #[derive(Debug, Clone)]
 pub struct WriteHalf {
-    sender: Sender<Vec<u8>>,
+    wire: Arc<Wire<Sender<Message<Vec<u8>>>>>,
+    reset: Arc<AtomicBool>,
+    stats: StatsCell,
+    buffer: FlushBuffer,
+    shaping: Option<Arc<Shaping>>,
 }
 
 impl WriteHalf {
-    async fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
-        self.sender
-            .send(bytes.to_vec())
-            .await
-            .map(|_| bytes.len())
-            .map_err(Into::into)
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Sets the flush mode of the stream this half belongs to (shared with clones of this half)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.buffer.set_mode(mode)
+    }
+
+    /// Returns the flush mode of the stream this half belongs to
+    pub fn flush_mode(&self) -> FlushMode {
+        self.buffer.mode()
+    }
+
+    /// Sends bytes to the stream (empty writes are not sent to the peer). Depending on the flush mode, bytes may be
+    /// buffered until the next flush.
+    pub(crate) fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+
+        let sent = match self.buffer.push(bytes) {
+            Some(buffered) if !buffered.is_empty() => {
+                self.transmit(&buffered)?;
+                true
+            }
+            _ => false,
+        };
+
+        self.stats.write(bytes.len(), sent);
+        Ok(bytes.len())
+    }
+
+    /// Sends bytes to the stream as one message
+    fn send_msg(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+
+        self.transmit(bytes)?;
+        self.stats.write(bytes.len(), true);
+
+        Ok(bytes.len())
+    }
+
+    /// Sends all the buffered bytes to the stream
+    fn send_buffered(&self) -> Result<(), Error> {
+        let sent = match self.buffer.flush() {
+            Some(buffered) => {
+                check_reset(&self.reset)?;
+                self.transmit(&buffered)?;
+                true
+            }
+            None => false,
+        };
+
+        self.stats.flush(sent);
+        Ok(())
+    }
+
+    fn transmit(&self, bytes: &[u8]) -> Result<(), Error> {
+        match &self.shaping {
+            Some(shaping) => shaping.apply(bytes, |chunk| self.wire.send(chunk.to_vec())),
+            None => self.wire.send(bytes.to_vec()),
+        }
+        .map_err(Into::into)
     }
 }
 
 impl AsyncWrite for WriteHalf {
     fn poll_write(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        let mut future = Box::pin(self.get_mut().send(buf));
-        let result = ready!(future.as_mut().poll(cx));
-
-        Poll::Ready(result.map_err(Into::into))
+        Poll::Ready(self.get_mut().send(buf).map_err(Into::into))
     }
 
     fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        Poll::Ready(self.send_buffered().map_err(Into::into))
     }
 
     fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
-        let _ = self.sender.close();
-        Poll::Ready(Ok(()))
+        let result = self.send_buffered();
+        self.wire.close();
+        Poll::Ready(result.map_err(Into::into))
+    }
+}
+
+impl Deliver for Sender<Message<Vec<u8>>> {
+    type Packet = Vec<u8>;
+
+    fn deliver(&self, message: Message<Vec<u8>>) -> Result<(), Inner> {
+        self.try_send(message).map_err(|_| Inner::AsyncSendError)
+    }
+
+    fn close(self) {
+        Sender::close(&self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{error::ErrorKind, futures::Direction};
+
+    #[tokio::test]
+    async fn check_peek() {
+        let (mut sender, mut receiver) = MockStream::pair();
+
+        assert!(matches!(sender.write_half.send(&[1, 2, 3, 4]), Ok(4)));
+
+        let mut buf = [0; 2];
+        assert!(matches!(receiver.peek(&mut buf).await, Ok(2)));
+        assert_eq!([1, 2], buf);
+
+        let mut buf = [0; 8];
+        assert!(matches!(receiver.peek(&mut buf).await, Ok(4)));
+        assert!(matches!(receiver.read_half.receive(&mut buf).await, Ok(4)));
+        assert_eq!([1, 2, 3, 4], buf[..4]);
+    }
+
+    #[tokio::test]
+    async fn check_controller() {
+        let (mut client, mut server) = MockStream::pair();
+        let controller = client.controller();
+        let mut buf = [0; 8];
+
+        controller.pause(Direction::Outgoing);
+        assert!(matches!(client.write_half.send(&[1, 2]), Ok(2)));
+        controller.resume(Direction::Outgoing);
+        assert!(matches!(server.read_half.receive(&mut buf).await, Ok(2)));
+
+        controller.reset();
+        let error = server.read_half.receive(&mut buf).await.unwrap_err();
+        assert_eq!(ErrorKind::ConnectionReset, error.kind());
+    }
+
+    #[cfg(any(feature = "async-tokio", feature = "sync"))]
+    #[test]
+    fn check_script_with_links() {
+        use std::time::Duration;
+
+        use crate::{futures::ChunkPolicy, link::Link};
+
+        let script = Script::new().links(Link::new().delay(Duration::from_millis(10)), Link::new());
+        let error = MockStream::pair_with_script(script).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+
+        assert!(MockStream::pair_with_script(ChunkPolicy::new().split_at(1)).is_ok());
+
+        let script = Script::new().links(Link::new().seed(7), Link::new());
+        assert!(MockStream::pair_with_script(script).is_ok());
     }
 }
@@ -1,43 +1,162 @@
 use std::{
     io::{self, Read, Write},
-    sync::mpsc::{channel, Receiver, Sender},
+    net::SocketAddr,
+    sync::{mpsc::RecvTimeoutError, Arc, PoisonError},
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::{error::Error, sync::Handle};
+use crate::{
+    controller::{self, check_reset, Deliver, Message, Wire},
+    datagram::copy_msg,
+    error::{Error, Inner},
+    flush::FlushBuffer,
+    lifecycle::Tracked,
+    link::LinkState,
+    primitives::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Mutex, MutexGuard,
+    },
+    recording::Recorder,
+    script::Shaping,
+    socket_options::{default_addrs, SharedOptions},
+    stats::StatsCell,
+    sync::{
+        Controller, FlushMode, Handle, Link, Recording, Script, Simulation, SocketOptions, Stats,
+    },
+};
 
 /// Synchronous mock IO stream
 pub struct MockStream {
     read_half: ReadHalf,
     write_half: WriteHalf,
+    options: SharedOptions,
+    controller: Controller,
+    recorder: Recorder,
 }
 
 impl MockStream {
     /// Connects to a mock IO listener
     pub fn connect(handle: &Handle) -> Result<Self, Error> {
         let (stream_1, stream_2) = Self::pair();
+
+        if let Some(simulation) = handle.simulation() {
+            stream_1.controller.simulate(simulation);
+        }
+
+        let tracked = Tracked {
+            lifecycle: handle.lifecycle().clone(),
+            id: handle.lifecycle().allocate(),
+        };
+        let id = tracked.id;
+
+        stream_1
+            .recorder
+            .track(tracked, stream_1.options.local_addr());
+        stream_2.read_half.stats.attach(handle.aggregate());
         handle.send(stream_2)?;
+        handle.lifecycle().connect(id);
         Ok(stream_1)
     }
 
     /// Creates a pair of connected mock streams
     pub fn pair() -> (Self, Self) {
+        Self::pair_with_links(Link::new(), Link::new())
+    }
+
+    /// Creates a pair of connected mock streams where data from first stream to second stream is carried by `link_1`
+    /// and data from second stream to first stream is carried by `link_2`
+    pub fn pair_with_links(link_1: Link, link_2: Link) -> (Self, Self) {
+        let (addr_1, addr_2) = default_addrs();
+        Self::new_pair(link_1, link_2, addr_1, addr_2)
+    }
+
+    /// Creates a pair of connected mock streams with given addresses (first stream is bound to `addr_1` and second
+    /// stream is bound to `addr_2`)
+    pub fn pair_with_addrs(addr_1: SocketAddr, addr_2: SocketAddr) -> (Self, Self) {
+        Self::new_pair(Link::new(), Link::new(), addr_1, addr_2)
+    }
+
+    /// Creates a pair of connected mock streams which follow given script (chunk policies, fault plans and links)
+    ///
+    /// Never returns an error with this backend (the `async-futures` backend rejects scripts with links).
+    pub fn pair_with_script(script: impl Into<Script>) -> Result<(Self, Self), Error> {
+        let script = script.into();
+        let (link_1, link_2) = script.links.clone();
+        let (addr_1, addr_2) = default_addrs();
+
+        let (mut stream_1, mut stream_2) = Self::new_pair(link_1, link_2, addr_1, addr_2);
+        let (shaping_1, shaping_2) = script.shaping(&stream_1.controller, &stream_2.controller);
+        stream_1.write_half.shaping = Some(Arc::new(shaping_1));
+        stream_2.write_half.shaping = Some(Arc::new(shaping_2));
+
+        Ok((stream_1, stream_2))
+    }
+
+    /// Creates a pair of connected mock streams whose deliveries (in both directions) are scheduled by given
+    /// simulation
+    pub fn pair_with_simulation(simulation: &Simulation) -> (Self, Self) {
+        let (stream_1, stream_2) = Self::pair();
+        stream_1.controller.simulate(simulation);
+        (stream_1, stream_2)
+    }
+
+    fn new_pair(
+        link_1: Link,
+        link_2: Link,
+        addr_1: SocketAddr,
+        addr_2: SocketAddr,
+    ) -> (Self, Self) {
         let (sender_1, receiver_1) = channel();
         let (sender_2, receiver_2) = channel();
+        let (options_1, options_2) = SharedOptions::pair(addr_1, addr_2);
+        let (recorder, tap_1, tap_2) = Recorder::pair(addr_1, addr_2);
+        let (stats_1, stats_2) = StatsCell::pair();
+        let (blocking_1, blocking_2) = (Blocking::default(), Blocking::default());
+
+        let wire_1 = Arc::new(Wire::new(sender_2, tap_1, stats_1.clone()));
+        let wire_2 = Arc::new(Wire::new(sender_1, tap_2, stats_2.clone()));
+        let (controller_1, controller_2, reset) = controller::pair(&wire_1, &wire_2);
 
         let stream_1 = Self {
-            read_half: ReadHalf {
-                receiver: receiver_1,
-                remaining: Default::default(),
-            },
-            write_half: WriteHalf { sender: sender_2 },
+            read_half: ReadHalf::new(
+                receiver_1,
+                reset.clone(),
+                blocking_1.clone(),
+                stats_1.clone(),
+            ),
+            write_half: WriteHalf::new(
+                wire_1,
+                link_1,
+                reset.clone(),
+                blocking_1,
+                stats_1,
+                FlushBuffer::new(controller_1.unflushed()),
+            ),
+            options: options_1,
+            controller: controller_1,
+            recorder: recorder.clone(),
         };
 
         let stream_2 = Self {
-            read_half: ReadHalf {
-                receiver: receiver_2,
-                remaining: Default::default(),
-            },
-            write_half: WriteHalf { sender: sender_1 },
+            read_half: ReadHalf::new(
+                receiver_2,
+                reset.clone(),
+                blocking_2.clone(),
+                stats_2.clone(),
+            ),
+            write_half: WriteHalf::new(
+                wire_2,
+                link_2,
+                reset,
+                blocking_2,
+                stats_2,
+                FlushBuffer::new(controller_2.unflushed()),
+            ),
+            options: options_2,
+            controller: controller_2,
+            recorder,
         };
 
         (stream_1, stream_2)
@@ -47,6 +166,164 @@ impl MockStream {
     pub fn split(self) -> (ReadHalf, WriteHalf) {
         (self.read_half, self.write_half)
     }
+
+    pub(crate) fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send(bytes)
+    }
+
+    pub(crate) fn send_msg(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send_msg(bytes)
+    }
+
+    pub(crate) fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.receive(buf)
+    }
+
+    pub(crate) fn receive_msg(&mut self, buf: &mut [u8]) -> Result<Option<(usize, bool)>, Error> {
+        self.read_half.receive_msg(buf)
+    }
+
+    /// Returns the address of this stream
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.local_addr())
+    }
+
+    /// Returns the address of the peer of this stream
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.peer_addr())
+    }
+
+    /// Starts recording the traffic on the connection of this stream (in both directions)
+    ///
+    /// Recording stops when the returned recording is dropped. This stream is treated as the client of the connection
+    /// when the recording is exported.
+    pub fn record(&self) -> Recording {
+        self.recorder
+            .start(self.options.local_addr(), self.options.peer_addr())
+    }
+
+    /// Returns a snapshot of the statistics of this stream
+    pub fn stats(&self) -> Stats {
+        self.read_half.stats()
+    }
+
+    /// Returns the ID of the connection of this stream in the lifecycle log of the listener it went through (if any)
+    pub(crate) fn connection_id(&self) -> Option<u64> {
+        self.recorder.connection_id()
+    }
+
+    /// Returns the number of bytes written by this stream which are not yet read by the peer
+    pub(crate) fn pending(&self) -> u64 {
+        self.read_half.stats.pending()
+    }
+
+    /// Sets the flush mode of this stream (i.e., when written data is delivered to the peer)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.write_half.set_flush_mode(mode)
+    }
+
+    /// Returns the flush mode of this stream
+    pub fn flush_mode(&self) -> FlushMode {
+        self.write_half.flush_mode()
+    }
+
+    /// Returns a controller for simulating network partitions and resets on the connection of this stream
+    pub fn controller(&self) -> Controller {
+        self.controller.clone()
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this stream
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<(), Error> {
+        self.options.update(|options| options.nodelay = nodelay);
+        Ok(())
+    }
+
+    /// Returns the value of the `TCP_NODELAY` option on this stream
+    pub fn nodelay(&self) -> Result<bool, Error> {
+        Ok(self.options.get().nodelay)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this stream
+    pub fn set_linger(&self, linger: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.linger = linger);
+        Ok(())
+    }
+
+    /// Returns the value of the `SO_LINGER` option on this stream
+    pub fn linger(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().linger)
+    }
+
+    /// Sets the value of the `IP_TTL` option on this stream
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), Error> {
+        self.options.update(|options| options.ttl = ttl);
+        Ok(())
+    }
+
+    /// Returns the value of the `IP_TTL` option on this stream
+    pub fn ttl(&self) -> Result<u32, Error> {
+        Ok(self.options.get().ttl)
+    }
+
+    /// Enables `SO_KEEPALIVE` with given idle time on this stream (`None` disables keepalive)
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.keepalive = keepalive);
+        Ok(())
+    }
+
+    /// Returns the keepalive idle time of this stream (`None` if keepalive is disabled)
+    pub fn keepalive(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().keepalive)
+    }
+
+    /// Returns a snapshot of all the socket options configured on this stream
+    pub fn options(&self) -> SocketOptions {
+        self.options.get()
+    }
+
+    /// Returns a snapshot of all the socket options configured on the peer of this stream
+    pub fn peer_options(&self) -> SocketOptions {
+        self.options.peer()
+    }
+
+    /// Receives data from the stream without removing it from the queue
+    ///
+    /// Successive calls return the same data. Blocks (or times out) in the same way as `read`.
+    pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.peek(buf)
+    }
+
+    /// Moves this stream into or out of non-blocking mode
+    ///
+    /// In non-blocking mode, `read` returns an error of kind `WouldBlock` instead of blocking when no data is
+    /// available.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        self.read_half.set_nonblocking(nonblocking)
+    }
+
+    /// Sets the read timeout of the stream (`None` means that `read` blocks indefinitely)
+    ///
+    /// Returns an error if a zero duration is passed.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.read_half.set_read_timeout(timeout)
+    }
+
+    /// Returns the read timeout of the stream
+    pub fn read_timeout(&self) -> Result<Option<Duration>, Error> {
+        self.read_half.read_timeout()
+    }
+
+    /// Sets the write timeout of the stream (`None` means that `write` blocks indefinitely)
+    ///
+    /// Returns an error if a zero duration is passed.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.write_half.set_write_timeout(timeout)
+    }
+
+    /// Returns the write timeout of the stream
+    pub fn write_timeout(&self) -> Result<Option<Duration>, Error> {
+        self.write_half.write_timeout()
+    }
 }
 
 impl Read for MockStream {
@@ -65,34 +342,186 @@ impl Write for MockStream {
     }
 }
 
+/// Data written to a mock stream along with the time at which it is delivered to the peer
+#[derive(Debug)]
+pub(crate) struct Packet {
+    bytes: Vec<u8>,
+    deliver_at: Option<Instant>,
+}
+
+impl AsRef<[u8]> for Packet {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 /// Read half of synchronous mock IO stream
 #[derive(Debug)]
 pub struct ReadHalf {
-    receiver: Receiver<Vec<u8>>,
+    receiver: Receiver<Message<Packet>>,
+    reset: Arc<AtomicBool>,
+    pending: Option<Packet>,
     remaining: Vec<u8>,
+    blocking: Blocking,
+    stats: StatsCell,
 }
 
 impl ReadHalf {
-    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-        let available_space = buf.len();
+    fn new(
+        receiver: Receiver<Message<Packet>>,
+        reset: Arc<AtomicBool>,
+        blocking: Blocking,
+        stats: StatsCell,
+    ) -> Self {
+        Self {
+            receiver,
+            reset,
+            pending: None,
+            remaining: Default::default(),
+            blocking,
+            stats,
+        }
+    }
+
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Moves the stream this half belongs to into or out of non-blocking mode (shared with the write half)
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        self.blocking.lock().nonblocking = nonblocking;
+        Ok(())
+    }
+
+    /// Sets the read timeout of the stream this half belongs to (`None` means that `read` blocks indefinitely)
+    ///
+    /// Returns an error if a zero duration is passed.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.blocking.lock().read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the read timeout of the stream this half belongs to
+    pub fn read_timeout(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.blocking.lock().read_timeout)
+    }
+
+    fn next(&mut self) -> Result<Vec<u8>, Inner> {
+        let (nonblocking, read_timeout) = {
+            let blocking = self.blocking.lock();
+            (blocking.nonblocking, blocking.read_timeout)
+        };
+        let deadline = read_timeout.map(|timeout| Instant::now() + timeout);
+
+        let message = match self.pending.take() {
+            Some(packet) => Message::Data(packet),
+            None if nonblocking => self.receiver.try_recv()?,
+            None => match deadline {
+                Some(deadline) => self
+                    .receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))?,
+                None => self.receiver.recv()?,
+            },
+        };
+
+        let packet = match message {
+            Message::Data(packet) => packet,
+            Message::Reset => {
+                self.reset.store(true, Ordering::SeqCst);
+                return Err(Inner::ConnectionReset);
+            }
+        };
+
+        if let Some(deliver_at) = packet.deliver_at {
+            let now = Instant::now();
+
+            if deliver_at > now && nonblocking {
+                self.pending = Some(packet);
+                return Err(Inner::WouldBlock);
+            }
+
+            if deliver_at > now {
+                match deadline {
+                    Some(deadline) if deadline < deliver_at => {
+                        thread::sleep(deadline.saturating_duration_since(now));
+                        self.pending = Some(packet);
+                        return Err(RecvTimeoutError::Timeout.into());
+                    }
+                    _ => thread::sleep(deliver_at - now),
+                }
+            }
+        }
+
+        Ok(packet.bytes)
+    }
+
+    /// Receives data from the read half without removing it from the queue
+    ///
+    /// Successive calls return the same data. Blocks (or times out) in the same way as `read`.
+    pub fn peek(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.fill()?;
+
+        let len = buf.len().min(self.remaining.len());
+        buf[..len].copy_from_slice(&self.remaining[..len]);
+
+        Ok(len)
+    }
+
+    /// Receives the next message if all the previous bytes have been read. Returns `false` (and remaining bytes stay
+    /// empty) if the peer has been dropped (i.e., on EOF).
+    fn fill(&mut self) -> Result<bool, Error> {
+        check_reset(&self.reset)?;
 
         if self.remaining.is_empty() {
-            self.remaining = self.receiver.recv()?;
+            match self.next() {
+                Ok(bytes) => {
+                    self.stats.message();
+                    self.remaining = bytes;
+                }
+                Err(Inner::SyncRecvError(_)) => return Ok(false),
+                Err(error) => return Err(error.into()),
+            }
         }
 
+        Ok(true)
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let available_space = buf.len();
+
+        self.fill()?;
+
         let remaining_len = self.remaining.len();
 
-        if remaining_len > available_space {
+        let len = if remaining_len > available_space {
             buf.copy_from_slice(&self.remaining[..available_space]);
             self.remaining = self.remaining[available_space..].to_vec();
 
-            Ok(available_space)
+            available_space
         } else {
             buf[..remaining_len].copy_from_slice(&self.remaining);
             self.remaining = Default::default();
 
-            Ok(remaining_len)
+            remaining_len
+        };
+
+        self.stats.read(len, len);
+        Ok(len)
+    }
+
+    /// Receives exactly one message (i.e., the bytes of one write) and returns its length along with a flag which is
+    /// `true` if the message was truncated to fit in the buffer (`None` on EOF)
+    fn receive_msg(&mut self, buf: &mut [u8]) -> Result<Option<(usize, bool)>, Error> {
+        if !self.fill()? {
+            return Ok(None);
         }
+
+        let message = std::mem::take(&mut self.remaining);
+        let (len, truncated) = copy_msg(&message, buf);
+        self.stats.read(len, message.len());
+
+        Ok(Some((len, truncated)))
     }
 }
 
@@ -105,16 +534,139 @@ impl Read for ReadHalf {
 /// Write half of synchronous mock IO stream
 #[derive(Debug, Clone)]
 pub struct WriteHalf {
-    sender: Sender<Vec<u8>>,
+    wire: Arc<Wire<Sender<Message<Packet>>>>,
+    link: Option<Arc<Mutex<LinkState<Instant>>>>,
+    reset: Arc<AtomicBool>,
+    blocking: Blocking,
+    stats: StatsCell,
+    buffer: FlushBuffer,
+    shaping: Option<Arc<Shaping>>,
 }
 
 impl WriteHalf {
-    /// Sends bytes to the stream
+    fn new(
+        wire: Arc<Wire<Sender<Message<Packet>>>>,
+        link: Link,
+        reset: Arc<AtomicBool>,
+        blocking: Blocking,
+        stats: StatsCell,
+        buffer: FlushBuffer,
+    ) -> Self {
+        Self {
+            wire,
+            link: LinkState::new(link).map(|state| Arc::new(Mutex::new(state))),
+            reset,
+            blocking,
+            stats,
+            buffer,
+            shaping: None,
+        }
+    }
+
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Sets the flush mode of the stream this half belongs to (shared with clones of this half)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.buffer.set_mode(mode)
+    }
+
+    /// Returns the flush mode of the stream this half belongs to
+    pub fn flush_mode(&self) -> FlushMode {
+        self.buffer.mode()
+    }
+
+    /// Moves the stream this half belongs to into or out of non-blocking mode (shared with the read half and clones of
+    /// this half)
+    ///
+    /// Writes are buffered without any limit, so they never block (or return `WouldBlock`) in either mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        self.blocking.lock().nonblocking = nonblocking;
+        Ok(())
+    }
+
+    /// Sets the write timeout of the stream this half belongs to (`None` means that `write` blocks indefinitely)
+    ///
+    /// Returns an error if a zero duration is passed. Writes are buffered without any limit, so they never time out.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.blocking.lock().write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the write timeout of the stream this half belongs to
+    pub fn write_timeout(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.blocking.lock().write_timeout)
+    }
+
+    /// Sends bytes to the stream (empty writes are not sent to the peer). Depending on the flush mode, bytes may be
+    /// buffered until the next flush.
     fn send(&self, bytes: &[u8]) -> Result<usize, Error> {
-        self.sender
-            .send(bytes.to_vec())
-            .map(|_| bytes.len())
-            .map_err(Into::into)
+        check_reset(&self.reset)?;
+
+        let sent = match self.buffer.push(bytes) {
+            Some(buffered) if !buffered.is_empty() => {
+                self.transmit(&buffered)?;
+                true
+            }
+            _ => false,
+        };
+
+        self.stats.write(bytes.len(), sent);
+        Ok(bytes.len())
+    }
+
+    /// Sends bytes to the stream as one message
+    fn send_msg(&self, bytes: &[u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+        self.transmit(bytes)?;
+        self.stats.write(bytes.len(), true);
+
+        Ok(bytes.len())
+    }
+
+    /// Sends all the buffered bytes to the stream
+    fn send_buffered(&self) -> Result<(), Error> {
+        let sent = match self.buffer.flush() {
+            Some(buffered) => {
+                check_reset(&self.reset)?;
+                self.transmit(&buffered)?;
+                true
+            }
+            None => false,
+        };
+
+        self.stats.flush(sent);
+        Ok(())
+    }
+
+    /// Resets the connection once the peer reads all the data sent before
+    pub(crate) fn abort(&self) -> Result<(), Error> {
+        self.wire.abort().map_err(Into::into)
+    }
+
+    fn transmit(&self, bytes: &[u8]) -> Result<(), Error> {
+        match &self.shaping {
+            Some(shaping) => shaping.apply(bytes, |chunk| self.transmit_chunk(chunk)),
+            None => self.transmit_chunk(bytes),
+        }
+        .map_err(Into::into)
+    }
+
+    fn transmit_chunk(&self, bytes: &[u8]) -> Result<(), Inner> {
+        let deliver_at = self.link.as_ref().map(|link| {
+            link.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .schedule(Instant::now(), bytes.len())
+        });
+
+        let packet = Packet {
+            bytes: bytes.to_vec(),
+            deliver_at,
+        };
+
+        self.wire.send(packet)
     }
 }
 
@@ -124,7 +676,42 @@ impl Write for WriteHalf {
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+        self.send_buffered().map_err(Into::into)
+    }
+}
+
+impl Deliver for Sender<Message<Packet>> {
+    type Packet = Packet;
+
+    fn deliver(&self, message: Message<Packet>) -> Result<(), Inner> {
+        self.send(message).map_err(|_| Inner::SyncSendError)
+    }
+}
+
+/// Blocking mode and timeouts of a mock stream (shared by its halves and clones of its write half, like the socket
+/// options of a `TcpStream`)
+#[derive(Debug, Clone, Default)]
+struct Blocking {
+    inner: Arc<Mutex<BlockingState>>,
+}
+
+#[derive(Debug, Default)]
+struct BlockingState {
+    nonblocking: bool,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Blocking {
+    fn lock(&self) -> MutexGuard<'_, BlockingState> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+pub(crate) fn check_timeout(timeout: Option<Duration>) -> Result<Option<Duration>, Error> {
+    match timeout {
+        Some(timeout) if timeout == Duration::ZERO => Err(Inner::ZeroTimeout.into()),
+        timeout => Ok(timeout),
     }
 }
 
@@ -132,6 +719,8 @@ impl Write for WriteHalf {
 mod tests {
     use super::*;
 
+    use crate::sync::{Direction, RecordedEvent};
+
     #[test]
     fn check_stream_communication() {
         let one = 1u64.to_be_bytes().to_vec();
@@ -154,4 +743,215 @@ mod tests {
         assert!(matches!(receiver.read(&mut buf), Ok(4)));
         assert_eq!(one[4..], buf[..]);
     }
+
+    #[test]
+    fn check_peek() {
+        let (mut sender, mut receiver) = MockStream::pair();
+
+        sender.write_all(&[1, 2, 3, 4]).unwrap();
+
+        let mut buf = [0; 2];
+        assert_eq!(2, receiver.peek(&mut buf).unwrap());
+        assert_eq!([1, 2], buf);
+
+        let mut buf = [0; 8];
+        assert_eq!(4, receiver.peek(&mut buf).unwrap());
+        assert_eq!(4, receiver.read(&mut buf).unwrap());
+        assert_eq!([1, 2, 3, 4], buf[..4]);
+    }
+
+    #[test]
+    fn check_link_delay() {
+        let delay = Duration::from_millis(50);
+        let (mut sender, mut receiver) =
+            MockStream::pair_with_links(Link::new().delay(delay), Link::new());
+        let mut buf = [0; 8];
+
+        let start = Instant::now();
+        sender.write_all(&[1, 2, 3, 4]).unwrap();
+
+        receiver.set_nonblocking(true).unwrap();
+        let error = receiver.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        receiver.set_nonblocking(false).unwrap();
+        assert_eq!(4, receiver.read(&mut buf).unwrap());
+        assert!(start.elapsed() >= delay);
+    }
+
+    #[test]
+    fn check_controller() {
+        let (mut client, mut server) = MockStream::pair();
+        let recording = client.record();
+        let controller = server.controller();
+        let mut buf = [0; 8];
+
+        server.set_nonblocking(true).unwrap();
+
+        controller.pause(Direction::Incoming);
+        client.write_all(&[1, 2]).unwrap();
+        let error = server.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        controller.discard(Direction::Incoming);
+        controller.resume(Direction::Incoming);
+        client.write_all(&[3, 4]).unwrap();
+        assert_eq!(2, server.read(&mut buf).unwrap());
+        assert_eq!([3, 4], buf[..2]);
+
+        controller.blackhole(Direction::Both);
+        client.write_all(&[5, 6]).unwrap();
+        server.write_all(&[7, 8]).unwrap();
+        let error = server.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        controller.resume(Direction::Both);
+        thread::spawn(move || controller.reset());
+
+        server.set_nonblocking(false).unwrap();
+        let error = server.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+        let error = client.write(&[9]).unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+
+        // Blackholed data is not recorded and the reset is recorded as sent by the server
+        let events = recording.events();
+        assert_eq!(3, events.len());
+        assert!(matches!(&events[1], RecordedEvent::Data { bytes, .. } if bytes == &[3, 4]));
+        assert!(matches!(
+            events[2],
+            RecordedEvent::Reset { from, .. } if from == server.local_addr().unwrap()
+        ));
+    }
+
+    #[test]
+    fn check_socket_options() {
+        let (client, server) = MockStream::pair();
+
+        client.set_nodelay(true).unwrap();
+        client.set_ttl(32).unwrap();
+        client.set_keepalive(Some(Duration::from_secs(60))).unwrap();
+
+        assert!(client.nodelay().unwrap());
+        assert_eq!(32, client.ttl().unwrap());
+        assert_eq!(None, client.linger().unwrap());
+
+        let options = server.peer_options();
+        assert_eq!(client.options(), options);
+        assert_eq!(Some(Duration::from_secs(60)), options.keepalive);
+        assert_eq!(SocketOptions::default(), server.options());
+    }
+
+    #[test]
+    fn check_nonblocking_and_timeouts() {
+        let (mut sender, mut receiver) = MockStream::pair();
+        let mut buf = [0; 8];
+
+        receiver.set_nonblocking(true).unwrap();
+        let error = receiver.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        sender.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(3, receiver.read(&mut buf).unwrap());
+
+        receiver.set_nonblocking(false).unwrap();
+        assert!(receiver.set_read_timeout(Some(Duration::ZERO)).is_err());
+        receiver
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(10)),
+            receiver.read_timeout().unwrap()
+        );
+
+        let error = receiver.read(&mut buf).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+        let (mut read_half, write_half) = receiver.split();
+        let clone = write_half.clone();
+        clone
+            .set_write_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            write_half.write_timeout().unwrap()
+        );
+
+        clone.set_nonblocking(true).unwrap();
+        read_half.set_read_timeout(None).unwrap();
+        let error = read_half.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+    }
+
+    #[test]
+    fn check_flush_modes() {
+        let (mut sender, mut receiver) = MockStream::pair();
+        sender.set_flush_mode(FlushMode::Buffered { threshold: 4 });
+        receiver.set_nonblocking(true).unwrap();
+        let mut buf = [0; 8];
+
+        sender.write_all(&[1, 2]).unwrap();
+        let error = receiver.read(&mut buf).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, error.kind());
+
+        sender.flush().unwrap();
+        assert_eq!(2, receiver.read(&mut buf).unwrap());
+
+        sender.write_all(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(4, receiver.read(&mut buf).unwrap());
+        assert_eq!(2, sender.stats().messages_written);
+    }
+
+    #[test]
+    #[should_panic(expected = "unflushed data")]
+    fn check_strict_flush_mode() {
+        let (mut sender, _receiver) = MockStream::pair();
+        let controller = sender.controller();
+        sender.set_flush_mode(FlushMode::Strict { threshold: 1024 });
+
+        thread::spawn(move || sender.write_all(&[1, 2, 3]).unwrap())
+            .join()
+            .unwrap();
+
+        controller.assert_flushed();
+    }
+
+    #[test]
+    fn check_simulation_replay() {
+        fn deliveries(seed: u64) -> Vec<u8> {
+            let simulation = Simulation::new(seed);
+            let mut pairs: Vec<_> = (0..4)
+                .map(|_| MockStream::pair_with_simulation(&simulation))
+                .collect();
+            let mut order = Vec::new();
+
+            for (i, (client, server)) in pairs.iter_mut().enumerate() {
+                client.write_all(&[i as u8]).unwrap();
+                server.set_nonblocking(true).unwrap();
+            }
+
+            while simulation.step() {
+                for (_, server) in pairs.iter_mut() {
+                    let mut buf = [0; 1];
+
+                    if let Ok(1) = server.read(&mut buf) {
+                        order.push(buf[0]);
+                    }
+                }
+            }
+
+            order
+        }
+
+        let order = deliveries(42);
+        assert_eq!(order, deliveries(42));
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(vec![0, 1, 2, 3], sorted);
+
+        assert!((0..16).any(|seed| deliveries(seed) != order));
+    }
 }
@@ -1,19 +1,42 @@
 use std::{
-    future::Future,
+    future::{poll_fn, Future},
     io,
+    net::SocketAddr,
     pin::Pin,
+    sync::{Arc, PoisonError},
     task::{Context, Poll},
+    time::Duration,
 };
 
 use pin_project_lite::pin_project;
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadBuf},
-    sync::mpsc::{
-        unbounded_channel as unbounded, UnboundedReceiver as Receiver, UnboundedSender as Sender,
-    },
+    time::{self, Instant, Sleep},
 };
 
-use crate::{error::Error, tokio::Handle};
+use crate::{
+    controller::{self, check_reset, Deliver, Message, Wire},
+    datagram::copy_msg,
+    error::{Error, Inner},
+    flush::FlushBuffer,
+    lifecycle::Tracked,
+    link::LinkState,
+    primitives::{
+        atomic::AtomicBool,
+        unbounded::{
+            error::TryRecvError, unbounded_channel as unbounded, UnboundedReceiver as Receiver,
+            UnboundedSender as Sender,
+        },
+        Mutex, MutexGuard,
+    },
+    recording::Recorder,
+    script::Shaping,
+    socket_options::{default_addrs, SharedOptions},
+    stats::StatsCell,
+    tokio::{
+        Controller, FlushMode, Handle, Link, Recording, Script, Simulation, SocketOptions, Stats,
+    },
+};
 
 macro_rules! ready {
     ($e:expr $(,)?) => {
@@ -32,6 +55,9 @@ pin_project! {
         read_half: ReadHalf,
         #[pin]
         write_half: WriteHalf,
+        options: SharedOptions,
+        controller: Controller,
+        recorder: Recorder,
     }
 }
 
@@ -39,29 +65,112 @@ impl MockStream {
     /// Connects to a mock IO listener
     pub fn connect(handle: &Handle) -> Result<Self, Error> {
         let (stream_1, stream_2) = Self::pair();
+
+        if let Some(simulation) = handle.simulation() {
+            stream_1.controller.simulate(simulation);
+        }
+
+        let tracked = Tracked {
+            lifecycle: handle.lifecycle().clone(),
+            id: handle.lifecycle().allocate(),
+        };
+        let id = tracked.id;
+
+        stream_1
+            .recorder
+            .track(tracked, stream_1.options.local_addr());
+        stream_2.read_half.stats.attach(handle.aggregate());
         handle.send(stream_2)?;
+        handle.lifecycle().connect(id);
         Ok(stream_1)
     }
 
     /// Creates a pair of connected mock streams
     pub fn pair() -> (Self, Self) {
+        Self::pair_with_links(Link::new(), Link::new())
+    }
+
+    /// Creates a pair of connected mock streams where data from first stream to second stream is carried by `link_1`
+    /// and data from second stream to first stream is carried by `link_2`
+    ///
+    /// Delays are computed using `tokio::time`, so they respect a paused clock (`tokio::time::pause`).
+    pub fn pair_with_links(link_1: Link, link_2: Link) -> (Self, Self) {
+        let (addr_1, addr_2) = default_addrs();
+        Self::new_pair(link_1, link_2, addr_1, addr_2)
+    }
+
+    /// Creates a pair of connected mock streams with given addresses (first stream is bound to `addr_1` and second
+    /// stream is bound to `addr_2`)
+    pub fn pair_with_addrs(addr_1: SocketAddr, addr_2: SocketAddr) -> (Self, Self) {
+        Self::new_pair(Link::new(), Link::new(), addr_1, addr_2)
+    }
+
+    /// Creates a pair of connected mock streams which follow given script (chunk policies, fault plans and links)
+    ///
+    /// Never returns an error with this backend (the `async-futures` backend rejects scripts with links).
+    pub fn pair_with_script(script: impl Into<Script>) -> Result<(Self, Self), Error> {
+        let script = script.into();
+        let (link_1, link_2) = script.links.clone();
+        let (addr_1, addr_2) = default_addrs();
+
+        let (mut stream_1, mut stream_2) = Self::new_pair(link_1, link_2, addr_1, addr_2);
+        let (shaping_1, shaping_2) = script.shaping(&stream_1.controller, &stream_2.controller);
+        stream_1.write_half.shaping = Some(Arc::new(shaping_1));
+        stream_2.write_half.shaping = Some(Arc::new(shaping_2));
+
+        Ok((stream_1, stream_2))
+    }
+
+    /// Creates a pair of connected mock streams whose deliveries (in both directions) are scheduled by given
+    /// simulation
+    pub fn pair_with_simulation(simulation: &Simulation) -> (Self, Self) {
+        let (stream_1, stream_2) = Self::pair();
+        stream_1.controller.simulate(simulation);
+        (stream_1, stream_2)
+    }
+
+    fn new_pair(
+        link_1: Link,
+        link_2: Link,
+        addr_1: SocketAddr,
+        addr_2: SocketAddr,
+    ) -> (Self, Self) {
         let (sender_1, receiver_1) = unbounded();
         let (sender_2, receiver_2) = unbounded();
+        let (options_1, options_2) = SharedOptions::pair(addr_1, addr_2);
+        let (recorder, tap_1, tap_2) = Recorder::pair(addr_1, addr_2);
+        let (stats_1, stats_2) = StatsCell::pair();
+
+        let wire_1 = Arc::new(Wire::new(sender_2, tap_1, stats_1.clone()));
+        let wire_2 = Arc::new(Wire::new(sender_1, tap_2, stats_2.clone()));
+        let (controller_1, controller_2, reset) = controller::pair(&wire_1, &wire_2);
 
         let stream_1 = Self {
-            read_half: ReadHalf {
-                receiver: receiver_1,
-                remaining: Default::default(),
-            },
-            write_half: WriteHalf { sender: sender_2 },
+            read_half: ReadHalf::new(receiver_1, reset.clone(), stats_1.clone()),
+            write_half: WriteHalf::new(
+                wire_1,
+                link_1,
+                reset.clone(),
+                stats_1,
+                FlushBuffer::new(controller_1.unflushed()),
+            ),
+            options: options_1,
+            controller: controller_1,
+            recorder: recorder.clone(),
         };
 
         let stream_2 = Self {
-            read_half: ReadHalf {
-                receiver: receiver_2,
-                remaining: Default::default(),
-            },
-            write_half: WriteHalf { sender: sender_1 },
+            read_half: ReadHalf::new(receiver_2, reset.clone(), stats_2.clone()),
+            write_half: WriteHalf::new(
+                wire_2,
+                link_2,
+                reset,
+                stats_2,
+                FlushBuffer::new(controller_2.unflushed()),
+            ),
+            options: options_2,
+            controller: controller_2,
+            recorder,
         };
 
         (stream_1, stream_2)
@@ -71,6 +180,172 @@ impl MockStream {
     pub fn split(self) -> (ReadHalf, WriteHalf) {
         (self.read_half, self.write_half)
     }
+
+    pub(crate) fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send(bytes)
+    }
+
+    pub(crate) fn send_msg(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        self.write_half.send_msg(bytes)
+    }
+
+    pub(crate) async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut buf = ReadBuf::new(buf);
+        self.read_half.receive(&mut buf).await?;
+
+        Ok(buf.filled().len())
+    }
+
+    pub(crate) async fn receive_msg(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<(usize, bool)>, Error> {
+        self.read_half.receive_msg(buf).await
+    }
+
+    /// Returns the address of this stream
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.local_addr())
+    }
+
+    /// Returns the address of the peer of this stream
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.options.peer_addr())
+    }
+
+    /// Starts recording the traffic on the connection of this stream (in both directions)
+    ///
+    /// Recording stops when the returned recording is dropped. This stream is treated as the client of the connection
+    /// when the recording is exported.
+    pub fn record(&self) -> Recording {
+        self.recorder
+            .start(self.options.local_addr(), self.options.peer_addr())
+    }
+
+    /// Returns a snapshot of the statistics of this stream
+    pub fn stats(&self) -> Stats {
+        self.read_half.stats()
+    }
+
+    /// Returns the ID of the connection of this stream in the lifecycle log of the listener it went through (if any)
+    pub(crate) fn connection_id(&self) -> Option<u64> {
+        self.recorder.connection_id()
+    }
+
+    /// Returns the number of bytes written by this stream which are not yet read by the peer
+    pub(crate) fn pending(&self) -> u64 {
+        self.read_half.stats.pending()
+    }
+
+    /// Sets the flush mode of this stream (i.e., when written data is delivered to the peer)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.write_half.set_flush_mode(mode)
+    }
+
+    /// Returns the flush mode of this stream
+    pub fn flush_mode(&self) -> FlushMode {
+        self.write_half.flush_mode()
+    }
+
+    /// Returns a controller for simulating network partitions and resets on the connection of this stream
+    pub fn controller(&self) -> Controller {
+        self.controller.clone()
+    }
+
+    /// Sets the value of the `TCP_NODELAY` option on this stream
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<(), Error> {
+        self.options.update(|options| options.nodelay = nodelay);
+        Ok(())
+    }
+
+    /// Returns the value of the `TCP_NODELAY` option on this stream
+    pub fn nodelay(&self) -> Result<bool, Error> {
+        Ok(self.options.get().nodelay)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this stream
+    pub fn set_linger(&self, linger: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.linger = linger);
+        Ok(())
+    }
+
+    /// Returns the value of the `SO_LINGER` option on this stream
+    pub fn linger(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().linger)
+    }
+
+    /// Sets the value of the `IP_TTL` option on this stream
+    pub fn set_ttl(&self, ttl: u32) -> Result<(), Error> {
+        self.options.update(|options| options.ttl = ttl);
+        Ok(())
+    }
+
+    /// Returns the value of the `IP_TTL` option on this stream
+    pub fn ttl(&self) -> Result<u32, Error> {
+        Ok(self.options.get().ttl)
+    }
+
+    /// Enables `SO_KEEPALIVE` with given idle time on this stream (`None` disables keepalive)
+    pub fn set_keepalive(&self, keepalive: Option<Duration>) -> Result<(), Error> {
+        self.options.update(|options| options.keepalive = keepalive);
+        Ok(())
+    }
+
+    /// Returns the keepalive idle time of this stream (`None` if keepalive is disabled)
+    pub fn keepalive(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.options.get().keepalive)
+    }
+
+    /// Returns a snapshot of all the socket options configured on this stream
+    pub fn options(&self) -> SocketOptions {
+        self.options.get()
+    }
+
+    /// Returns a snapshot of all the socket options configured on the peer of this stream
+    pub fn peer_options(&self) -> SocketOptions {
+        self.options.peer()
+    }
+
+    /// Receives data from the stream without removing it from the queue
+    ///
+    /// Successive calls return the same data. Waits until data is available.
+    ///
+    /// Only one task can wait for data at a time: if `peek` and `readable` are awaited concurrently (e.g., by tasks
+    /// sharing the stream in an `Arc`), only the task which polled last is woken up when data arrives and the others
+    /// may wait forever.
+    pub async fn peek(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.peek(buf).await
+    }
+
+    /// Waits for the stream to become readable
+    ///
+    /// The stream is readable when data is available or when the peer has been dropped.
+    ///
+    /// Only one task can wait for data at a time: if `peek` and `readable` are awaited concurrently (e.g., by tasks
+    /// sharing the stream in an `Arc`), only the task which polled last is woken up when data arrives and the others
+    /// may wait forever.
+    pub async fn readable(&self) -> Result<(), Error> {
+        self.read_half.readable().await
+    }
+
+    /// Waits for the stream to become writable
+    ///
+    /// Writes are buffered without any limit, so the stream is always writable.
+    pub async fn writable(&self) -> Result<(), Error> {
+        self.write_half.writable().await
+    }
+
+    /// Tries to read data from the stream into the provided buffer without waiting
+    ///
+    /// Returns an error of kind `WouldBlock` if no data is available.
+    pub fn try_read(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_half.try_read(buf)
+    }
+
+    /// Tries to write data to the stream without waiting
+    pub fn try_write(&self, buf: &[u8]) -> Result<usize, Error> {
+        self.write_half.try_write(buf)
+    }
 }
 
 impl AsyncRead for MockStream {
@@ -101,38 +376,218 @@ impl AsyncWrite for MockStream {
     }
 }
 
+/// Data written to a mock stream along with the time at which it is delivered to the peer
+#[derive(Debug)]
+pub(crate) struct Packet {
+    bytes: Vec<u8>,
+    deliver_at: Option<Instant>,
+}
+
+impl AsRef<[u8]> for Packet {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 /// Read half of asynchronous mock IO stream
 #[derive(Debug)]
 pub struct ReadHalf {
-    receiver: Receiver<Vec<u8>>,
+    state: Mutex<ReadState>,
+    reset: Arc<AtomicBool>,
+    stats: StatsCell,
+}
+
+/// Receiving state of a read half (behind a lock, so that the read half can be peeked and read through a shared
+/// reference like `tokio::net::TcpStream`)
+#[derive(Debug)]
+struct ReadState {
+    receiver: Receiver<Message<Packet>>,
+    pending: Option<Packet>,
+    delay: Option<Pin<Box<Sleep>>>,
     remaining: Vec<u8>,
 }
 
 impl ReadHalf {
-    async fn receive(&mut self, buf: &mut ReadBuf<'_>) -> Result<(), Error> {
-        let available_space = buf.remaining();
+    fn new(receiver: Receiver<Message<Packet>>, reset: Arc<AtomicBool>, stats: StatsCell) -> Self {
+        Self {
+            state: Mutex::new(ReadState {
+                receiver,
+                pending: None,
+                delay: None,
+                remaining: Default::default(),
+            }),
+            reset,
+            stats,
+        }
+    }
+
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Receives data from the read half without removing it from the queue
+    ///
+    /// Successive calls return the same data. Waits until data is available.
+    ///
+    /// Only one task can wait for data at a time: if `peek` and `readable` are awaited concurrently (e.g., by tasks
+    /// sharing the read half in an `Arc`), only the task which polled last is woken up when data arrives and the others
+    /// may wait forever.
+    pub async fn peek(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        poll_fn(|cx| {
+            let mut state = self.lock();
+            ready!(self.poll_fill(&mut state, cx))?;
+
+            let len = buf.len().min(state.remaining.len());
+            buf[..len].copy_from_slice(&state.remaining[..len]);
+
+            Poll::Ready(Ok(len))
+        })
+        .await
+    }
+
+    /// Waits for the read half to become readable
+    ///
+    /// The read half is readable when data is available or when the peer has been dropped.
+    ///
+    /// Only one task can wait for data at a time: if `peek` and `readable` are awaited concurrently (e.g., by tasks
+    /// sharing the read half in an `Arc`), only the task which polled last is woken up when data arrives and the others
+    /// may wait forever.
+    pub async fn readable(&self) -> Result<(), Error> {
+        poll_fn(|cx| self.poll_fill(&mut self.lock(), cx))
+            .await
+            .map(|_| ())
+    }
 
-        if self.remaining.is_empty() {
-            self.remaining = self
-                .receiver
-                .recv()
-                .await
-                .ok_or(crate::error::Inner::TokioRecvError)?;
+    /// Tries to read data from the read half into the provided buffer without waiting
+    ///
+    /// Returns an error of kind `WouldBlock` if no data is available.
+    pub fn try_read(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+        let mut state = self.lock();
+
+        if state.remaining.is_empty() {
+            let packet = match state.pending.take() {
+                Some(packet) => packet,
+                None => match state.receiver.try_recv() {
+                    Ok(Message::Data(packet)) => packet,
+                    Ok(Message::Reset) => return Err(Inner::ConnectionReset.into()),
+                    Err(TryRecvError::Disconnected) => return Ok(0),
+                    Err(error) => return Err(Inner::from(error).into()),
+                },
+            };
+
+            match packet.deliver_at {
+                Some(deliver_at) if deliver_at > Instant::now() => {
+                    state.pending = Some(packet);
+                    return Err(Inner::WouldBlock.into());
+                }
+                _ => {
+                    state.delay = None;
+                    self.stats.message();
+                    state.remaining = packet.bytes;
+                }
+            }
         }
 
-        let remaining_len = self.remaining.len();
+        let mut buf = ReadBuf::new(buf);
+        self.take(&mut state, &mut buf);
 
-        if remaining_len > available_space {
-            buf.put_slice(&self.remaining[..available_space]);
-            self.remaining = self.remaining[available_space..].to_vec();
+        Ok(buf.filled().len())
+    }
 
-            Ok(())
-        } else {
-            buf.put_slice(&self.remaining);
-            self.remaining = Default::default();
+    /// Receives the next message if all the previous bytes have been read. Returns `false` (and remaining bytes stay
+    /// empty) if the peer has been dropped (i.e., on EOF).
+    fn poll_fill(&self, state: &mut ReadState, cx: &mut Context<'_>) -> Poll<Result<bool, Error>> {
+        check_reset(&self.reset)?;
+
+        if !state.remaining.is_empty() {
+            return Poll::Ready(Ok(true));
+        }
 
-            Ok(())
+        if state.pending.is_none() {
+            match ready!(state.receiver.poll_recv(cx)) {
+                Some(Message::Data(packet)) => state.pending = Some(packet),
+                Some(Message::Reset) => return Poll::Ready(Err(Inner::ConnectionReset.into())),
+                None => return Poll::Ready(Ok(false)),
+            }
         }
+
+        if let Some(Packet {
+            deliver_at: Some(deliver_at),
+            ..
+        }) = state.pending
+        {
+            ready!(state
+                .delay
+                .get_or_insert_with(|| Box::pin(time::sleep_until(deliver_at)))
+                .as_mut()
+                .poll(cx));
+            state.delay = None;
+        }
+
+        if let Some(packet) = state.pending.take() {
+            self.stats.message();
+            state.remaining = packet.bytes;
+        }
+
+        Poll::Ready(Ok(true))
+    }
+
+    fn poll_receive(&self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), Error>> {
+        let mut state = self.lock();
+        ready!(self.poll_fill(&mut state, cx))?;
+        self.take(&mut state, buf);
+
+        Poll::Ready(Ok(()))
+    }
+
+    pub(crate) async fn receive(&mut self, buf: &mut ReadBuf<'_>) -> Result<(), Error> {
+        poll_fn(|cx| self.poll_receive(cx, buf)).await
+    }
+
+    /// Receives exactly one message (i.e., the bytes of one write) and returns its length along with a flag which is
+    /// `true` if the message was truncated to fit in the buffer (`None` on EOF)
+    async fn receive_msg(&mut self, buf: &mut [u8]) -> Result<Option<(usize, bool)>, Error> {
+        let message = poll_fn(|cx| {
+            let mut state = self.lock();
+
+            if !ready!(self.poll_fill(&mut state, cx))? {
+                return Poll::Ready(Ok(None));
+            }
+
+            Poll::Ready(Ok::<_, Error>(Some(std::mem::take(&mut state.remaining))))
+        })
+        .await?;
+
+        Ok(message.map(|message| {
+            let (len, truncated) = copy_msg(&message, buf);
+            self.stats.read(len, message.len());
+            (len, truncated)
+        }))
+    }
+
+    fn take(&self, state: &mut ReadState, buf: &mut ReadBuf<'_>) {
+        let available_space = buf.remaining();
+        let remaining_len = state.remaining.len();
+
+        let len = if remaining_len > available_space {
+            buf.put_slice(&state.remaining[..available_space]);
+            state.remaining = state.remaining[available_space..].to_vec();
+
+            available_space
+        } else {
+            buf.put_slice(&state.remaining);
+            state.remaining = Default::default();
+
+            remaining_len
+        };
+
+        self.stats.read(len, len);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReadState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
     }
 }
 
@@ -142,8 +597,7 @@ impl AsyncRead for ReadHalf {
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        let mut future = Box::pin(self.get_mut().receive(buf));
-        let result = ready!(future.as_mut().poll(cx));
+        let result = ready!(self.poll_receive(cx, buf));
 
         Poll::Ready(result.map_err(Into::into))
     }
@@ -152,15 +606,121 @@ impl AsyncRead for ReadHalf {
 /// Write half of asynchronous mock IO stream
 #[derive(Debug, Clone)]
 pub struct WriteHalf {
-    sender: Sender<Vec<u8>>,
+    wire: Arc<Wire<Sender<Message<Packet>>>>,
+    link: Option<Arc<Mutex<LinkState<Instant>>>>,
+    reset: Arc<AtomicBool>,
+    stats: StatsCell,
+    buffer: FlushBuffer,
+    shaping: Option<Arc<Shaping>>,
 }
 
 impl WriteHalf {
-    fn send(&mut self, bytes: &[u8]) -> Result<usize, Error> {
-        self.sender
-            .send(bytes.to_vec())
-            .map(|_| bytes.len())
-            .map_err(Into::into)
+    fn new(
+        wire: Arc<Wire<Sender<Message<Packet>>>>,
+        link: Link,
+        reset: Arc<AtomicBool>,
+        stats: StatsCell,
+        buffer: FlushBuffer,
+    ) -> Self {
+        Self {
+            wire,
+            link: LinkState::new(link).map(|state| Arc::new(Mutex::new(state))),
+            reset,
+            stats,
+            buffer,
+            shaping: None,
+        }
+    }
+
+    /// Returns a snapshot of the statistics of the stream this half belongs to
+    pub fn stats(&self) -> Stats {
+        self.stats.get()
+    }
+
+    /// Sets the flush mode of the stream this half belongs to (shared with clones of this half)
+    pub fn set_flush_mode(&self, mode: FlushMode) {
+        self.buffer.set_mode(mode)
+    }
+
+    /// Returns the flush mode of the stream this half belongs to
+    pub fn flush_mode(&self) -> FlushMode {
+        self.buffer.mode()
+    }
+
+    /// Waits for the write half to become writable
+    ///
+    /// Writes are buffered without any limit, so the write half is always writable.
+    pub async fn writable(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Tries to write data to the write half without waiting
+    pub fn try_write(&self, buf: &[u8]) -> Result<usize, Error> {
+        self.send(buf)
+    }
+
+    /// Sends bytes to the stream (empty writes are not sent to the peer). Depending on the flush mode, bytes may be
+    /// buffered until the next flush.
+    pub(crate) fn send(&self, bytes: &[u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+
+        let sent = match self.buffer.push(bytes) {
+            Some(buffered) if !buffered.is_empty() => {
+                self.transmit(&buffered)?;
+                true
+            }
+            _ => false,
+        };
+
+        self.stats.write(bytes.len(), sent);
+        Ok(bytes.len())
+    }
+
+    /// Sends bytes to the stream as one message
+    fn send_msg(&mut self, bytes: &[u8]) -> Result<usize, Error> {
+        check_reset(&self.reset)?;
+        self.transmit(bytes)?;
+        self.stats.write(bytes.len(), true);
+
+        Ok(bytes.len())
+    }
+
+    /// Sends all the buffered bytes to the stream
+    fn send_buffered(&self) -> Result<(), Error> {
+        let sent = match self.buffer.flush() {
+            Some(buffered) => {
+                check_reset(&self.reset)?;
+                self.transmit(&buffered)?;
+                true
+            }
+            None => false,
+        };
+
+        self.stats.flush(sent);
+        Ok(())
+    }
+
+    fn transmit(&self, bytes: &[u8]) -> Result<(), Error> {
+        match &self.shaping {
+            Some(shaping) => shaping.apply(bytes, |chunk| self.transmit_chunk(chunk)),
+            None => self.transmit_chunk(bytes),
+        }
+        .map_err(Into::into)
+    }
+
+    fn transmit_chunk(&self, bytes: &[u8]) -> Result<(), Inner> {
+        let deliver_at = self.link.as_ref().map(|link| {
+            link.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .schedule(Instant::now(), bytes.len())
+        });
+
+        let packet = Packet {
+            bytes: bytes.to_vec(),
+            deliver_at,
+        };
+
+        self.wire.send(packet)
     }
 }
 
@@ -174,11 +734,21 @@ impl AsyncWrite for WriteHalf {
     }
 
     fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        Poll::Ready(self.send_buffered().map_err(Into::into))
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
+        let result = self.send_buffered();
+        self.wire.close();
+        Poll::Ready(result.map_err(Into::into))
+    }
+}
+
+impl Deliver for Sender<Message<Packet>> {
+    type Packet = Packet;
+
+    fn deliver(&self, message: Message<Packet>) -> Result<(), Inner> {
+        self.send(message).map_err(|_| Inner::TokioSendError)
     }
 }
 
@@ -186,7 +756,15 @@ impl AsyncWrite for WriteHalf {
 mod tests {
     use super::*;
 
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use crate::{
+        error::ErrorKind,
+        tokio::{Direction, RecordedEvent, Side},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        task,
+    };
 
     #[tokio::test]
     async fn check_stream_communication() {
@@ -210,4 +788,139 @@ mod tests {
         assert!(matches!(receiver.read(&mut buf).await, Ok(4)));
         assert_eq!(one[4..], buf[..]);
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn check_link_delay_and_rate() {
+        let link = Link::new().delay(Duration::from_millis(100)).rate(1000);
+        let (mut sender, mut receiver) = MockStream::pair_with_links(link, Link::new());
+        let mut buf = [0; 500];
+
+        let start = Instant::now();
+        sender.write_all(&[0; 500]).await.unwrap();
+        sender.write_all(&[0; 500]).await.unwrap();
+
+        let error = receiver.try_read(&mut buf).unwrap_err();
+        assert_eq!(ErrorKind::WouldBlock, error.kind());
+
+        receiver.read_exact(&mut buf).await.unwrap();
+        assert_eq!(Duration::from_millis(600), start.elapsed());
+
+        receiver.read_exact(&mut buf).await.unwrap();
+        assert_eq!(Duration::from_millis(1100), start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn check_controller() {
+        let (mut client, mut server) = MockStream::pair();
+        let controller = client.controller();
+        let mut buf = [0; 8];
+
+        controller.blackhole(Direction::Incoming);
+        server.write_all(&[1, 2]).await.unwrap();
+        let error = client.try_read(&mut buf).unwrap_err();
+        assert_eq!(ErrorKind::WouldBlock, error.kind());
+
+        controller.resume(Direction::Incoming);
+        let reader = task::spawn(async move { server.read(&mut buf).await });
+
+        task::yield_now().await;
+        controller.reset();
+
+        let error = reader.await.unwrap().unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+        let error = client.write_all(&[3]).await.unwrap_err();
+        assert_eq!(io::ErrorKind::ConnectionReset, error.kind());
+    }
+
+    #[tokio::test]
+    async fn check_socket_options() {
+        let (client, server) = MockStream::pair();
+
+        client.set_nodelay(true).unwrap();
+        client.set_linger(Some(Duration::from_secs(1))).unwrap();
+
+        let options = server.peer_options();
+        assert!(options.nodelay);
+        assert_eq!(Some(Duration::from_secs(1)), options.linger);
+        assert_eq!(SocketOptions::default(), server.options());
+    }
+
+    #[tokio::test]
+    async fn check_peek_and_readiness() {
+        let (sender, receiver) = MockStream::pair();
+        let mut buf = [0; 8];
+
+        let error = receiver.try_read(&mut buf).unwrap_err();
+        assert_eq!(ErrorKind::WouldBlock, error.kind());
+
+        sender.writable().await.unwrap();
+        assert!(matches!(sender.try_write(&[1, 2, 3, 4]), Ok(4)));
+
+        receiver.readable().await.unwrap();
+
+        let mut buf = [0; 2];
+        assert!(matches!(receiver.peek(&mut buf).await, Ok(2)));
+        assert_eq!([1, 2], buf);
+
+        let mut buf = [0; 8];
+        assert!(matches!(receiver.try_read(&mut buf), Ok(4)));
+        assert_eq!([1, 2, 3, 4], buf[..4]);
+
+        drop(sender);
+        receiver.readable().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_flush_mode() {
+        let (mut sender, mut receiver) = MockStream::pair();
+        sender.set_flush_mode(FlushMode::Strict { threshold: 1024 });
+        let mut buf = [0; 8];
+
+        sender.write_all(&[1, 2, 3]).await.unwrap();
+        let error = receiver.try_read(&mut buf).unwrap_err();
+        assert_eq!(ErrorKind::WouldBlock, error.kind());
+
+        sender.flush().await.unwrap();
+        assert_eq!(3, receiver.read(&mut buf).await.unwrap());
+
+        let controller = sender.controller();
+        drop(sender);
+        controller.assert_flushed();
+    }
+
+    #[tokio::test]
+    async fn check_shutdown() {
+        let (mut client, mut server) = MockStream::pair();
+        let recording = client.record();
+        let mut buf = [0; 8];
+
+        client.write_all(&[1, 2]).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let error = client.write_all(&[3]).await.unwrap_err();
+        assert_eq!(io::ErrorKind::NotConnected, error.kind());
+
+        assert_eq!(2, server.read(&mut buf).await.unwrap());
+        assert_eq!(0, server.read(&mut buf).await.unwrap());
+
+        server.write_all(&[4]).await.unwrap();
+        assert_eq!(1, client.read(&mut buf).await.unwrap());
+
+        recording.timeline().assert_sequence(vec![
+            Side::Client.wrote([1, 2]),
+            Side::Client.shutdown(),
+            Side::Server.wrote([4]),
+        ]);
+
+        drop(client);
+        assert_eq!(
+            1,
+            recording
+                .timeline()
+                .events()
+                .iter()
+                .filter(|event| matches!(event, RecordedEvent::Shutdown { .. }))
+                .count()
+        );
+    }
 }
@@ -0,0 +1,2 @@
+#[cfg(feature = "sync")]
+pub mod sync;
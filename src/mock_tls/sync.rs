@@ -0,0 +1,206 @@
+use std::{io, ops::DerefMut, thread};
+
+use rustls::{ClientConnection, ConnectionCommon, ServerConnection, SideData, StreamOwned};
+
+use crate::{
+    error::{Error, Inner},
+    sync::{MockStream, TlsOptions},
+};
+
+/// TLS client over a synchronous mock IO stream
+pub type TlsClientStream = StreamOwned<ClientConnection, MockStream>;
+
+/// TLS server over a synchronous mock IO stream
+pub type TlsServerStream = StreamOwned<ServerConnection, MockStream>;
+
+impl MockStream {
+    /// Creates a pair of connected mock streams wrapped in TLS (first stream as client and second stream as server)
+    /// and completes the handshake between them
+    ///
+    /// Returns the error of the side which detected the failure if the handshake fails.
+    ///
+    /// ```rust
+    /// # use std::io::{Read, Write};
+    /// use mock_io::sync::{MockStream, TestCa, TlsOptions};
+    ///
+    /// let (mut client, mut server) = MockStream::tls_pair(&TlsOptions::new().alpn("h2")).unwrap();
+    /// assert_eq!(Some(&b"h2"[..]), client.conn.alpn_protocol());
+    /// assert_eq!(TestCa::certificate(), client.conn.peer_certificates().unwrap()[0]);
+    ///
+    /// client.write_all(b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// server.read_exact(&mut buf).unwrap();
+    /// assert_eq!(b"hello", &buf);
+    /// ```
+    pub fn tls_pair(options: &TlsOptions) -> Result<(TlsClientStream, TlsServerStream), Error> {
+        let (stream_1, stream_2) = Self::pair();
+
+        let mut client = stream_1.into_tls_client(options)?;
+        let mut server = stream_2.into_tls_server(options)?;
+
+        let server = thread::spawn(move || {
+            handshake(&mut server.conn, &mut server.sock)?;
+            Ok(server)
+        });
+
+        let client = handshake(&mut client.conn, &mut client.sock).map(|_| client);
+        let server = server.join().expect("TLS server thread panicked");
+
+        match (client, server) {
+            (Ok(client), Ok(server)) => Ok((client, server)),
+            (Err(error), Ok(_)) | (Ok(_), Err(error)) => Err(error),
+            (Err(client_error), Err(server_error)) => {
+                if detected_locally(&client_error) || !detected_locally(&server_error) {
+                    Err(client_error)
+                } else {
+                    Err(server_error)
+                }
+            }
+        }
+    }
+
+    /// Wraps the stream in TLS as a client configured by given options (the handshake is performed on first read or
+    /// write)
+    pub fn into_tls_client(self, options: &TlsOptions) -> Result<TlsClientStream, Error> {
+        let connection = ClientConnection::new(options.client_config(), options.server_name())?;
+        Ok(StreamOwned::new(connection, self))
+    }
+
+    /// Wraps the stream in TLS as a server configured by given options (the handshake is performed on first read or
+    /// write)
+    pub fn into_tls_server(self, options: &TlsOptions) -> Result<TlsServerStream, Error> {
+        let connection = ServerConnection::new(options.server_config())?;
+        Ok(StreamOwned::new(connection, self))
+    }
+}
+
+/// Drives the handshake of given connection until it is complete and all the handshake messages are sent
+fn handshake<C, S>(connection: &mut C, stream: &mut MockStream) -> Result<(), Error>
+where
+    C: DerefMut<Target = ConnectionCommon<S>>,
+    S: SideData,
+{
+    while connection.is_handshaking() {
+        connection.complete_io(stream).map_err(tls_error)?;
+    }
+
+    while connection.wants_write() {
+        connection.write_tls(stream)?;
+    }
+
+    Ok(())
+}
+
+/// Unwraps TLS errors returned by `rustls` as IO errors
+fn tls_error(error: io::Error) -> Error {
+    match error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<rustls::Error>())
+    {
+        Some(error) => Inner::Tls(error.clone()).into(),
+        None => error.into(),
+    }
+}
+
+/// Returns `true` if the failure was detected by the side returning given error (and not reported by its peer)
+fn detected_locally(error: &Error) -> bool {
+    use std::error::Error as _;
+
+    match error
+        .source()
+        .and_then(|source| source.downcast_ref::<rustls::Error>())
+    {
+        Some(rustls::Error::AlertReceived(_)) | None => false,
+        Some(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+
+    use rustls::CertificateError;
+
+    use crate::{
+        error::ErrorKind,
+        sync::{TestCa, TlsFault},
+    };
+
+    fn tls_error_of(options: TlsOptions) -> rustls::Error {
+        use std::error::Error as _;
+
+        let error = match MockStream::tls_pair(&options) {
+            Ok(_) => panic!("handshake succeeded"),
+            Err(error) => error,
+        };
+        assert_eq!(ErrorKind::Tls, error.kind());
+
+        error
+            .source()
+            .and_then(|source| source.downcast_ref::<rustls::Error>())
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn check_tls_pair() {
+        let options = TlsOptions::new()
+            .client_alpn(["h2", "http/1.1"])
+            .server_alpn(["http/1.1"])
+            .client_auth(true);
+
+        let (mut client, mut server) = MockStream::tls_pair(&options).unwrap();
+        assert_eq!(Some(&b"http/1.1"[..]), client.conn.alpn_protocol());
+        assert_eq!(Some(&b"http/1.1"[..]), server.conn.alpn_protocol());
+        assert_eq!(
+            Some(&[TestCa::certificate()][..]),
+            server.conn.peer_certificates()
+        );
+
+        server.write_all(b"hello").unwrap();
+
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(b"hello", &buf);
+
+        let (client, _server) = MockStream::tls_pair(&TlsOptions::new()).unwrap();
+        assert_eq!(None, client.conn.alpn_protocol());
+    }
+
+    #[test]
+    fn check_tls_faults() {
+        assert!(matches!(
+            tls_error_of(TlsOptions::new().fault(TlsFault::ExpiredCertificate)),
+            rustls::Error::InvalidCertificate(CertificateError::ExpiredContext { .. })
+                | rustls::Error::InvalidCertificate(CertificateError::Expired)
+        ));
+        assert_eq!(
+            rustls::Error::InvalidCertificate(CertificateError::UnknownIssuer),
+            tls_error_of(TlsOptions::new().fault(TlsFault::UntrustedCertificate))
+        );
+        assert!(matches!(
+            tls_error_of(TlsOptions::new().fault(TlsFault::WrongServerName)),
+            rustls::Error::InvalidCertificate(CertificateError::NotValidForNameContext { .. })
+                | rustls::Error::InvalidCertificate(CertificateError::NotValidForName)
+        ));
+        assert!(matches!(
+            tls_error_of(TlsOptions::new().fault(TlsFault::ProtocolMismatch)),
+            rustls::Error::PeerIncompatible(_)
+        ));
+        assert!(matches!(
+            tls_error_of(TlsOptions::new().fault(TlsFault::MissingClientCertificate)),
+            rustls::Error::NoCertificatesPresented
+        ));
+        assert_eq!(
+            rustls::Error::NoApplicationProtocol,
+            tls_error_of(
+                TlsOptions::new()
+                    .client_alpn(["h2"])
+                    .server_alpn(["http/1.1"])
+            )
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use async_channel::{unbounded, Receiver, Sender};
+
+use crate::{
+    datagram::{Datagram, DatagramSender, Registry, SocketCore},
+    error::Error,
+    futures::DatagramFaults,
+};
+
+/// Network of asynchronous mock datagram sockets
+///
+/// Sockets bound to the same network share an address space and can send datagrams to each other.
+///
+/// Each network is a separate address space: addresses of mock streams and listeners are not bound in any network and
+/// sockets of different backends can not be bound to the same network. Ports allocated for sockets bound to port `0`
+/// are taken from the ones assigned to mock streams created without explicit addresses though, so they never collide.
+#[derive(Debug, Clone, Default)]
+pub struct MockNetwork {
+    registry: Arc<Registry<Sender<Datagram>>>,
+}
+
+impl MockNetwork {
+    /// Creates a new mock network
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+/// Asynchronous mock datagram socket
+#[derive(Debug)]
+pub struct MockUdpSocket {
+    core: SocketCore<Sender<Datagram>>,
+    receiver: Receiver<Datagram>,
+}
+
+impl MockUdpSocket {
+    /// Binds a new socket to given address on the mock network. A free port is allocated if the port is `0`.
+    pub fn bind(network: &MockNetwork, addr: SocketAddr) -> Result<Self, Error> {
+        let (sender, receiver) = unbounded();
+
+        Ok(Self {
+            core: SocketCore::bind(&network.registry, addr, sender)?,
+            receiver,
+        })
+    }
+
+    /// Returns the address this socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.core.local_addr())
+    }
+
+    /// Returns the address of the peer this socket is connected to
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        self.core.peer_addr().map_err(Into::into)
+    }
+
+    /// Connects this socket to given address, so that `send` and `recv` can be used. Datagrams from any other address
+    /// are discarded.
+    pub fn connect(&self, addr: SocketAddr) -> Result<(), Error> {
+        self.core.connect(addr);
+        Ok(())
+    }
+
+    /// Sets the faults injected into the datagrams sent by this socket
+    pub fn set_faults(&self, faults: DatagramFaults) {
+        self.core.set_faults(faults)
+    }
+
+    /// Sends a datagram to given address. Datagrams sent to an address without any socket are silently dropped.
+    pub async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize, Error> {
+        Ok(self.core.send_to(buf, addr))
+    }
+
+    /// Sends a datagram to the connected peer
+    pub async fn send(&self, buf: &[u8]) -> Result<usize, Error> {
+        self.core.send(buf).map_err(Into::into)
+    }
+
+    /// Receives a datagram along with the address of its sender. The datagram is truncated if the buffer is too small.
+    ///
+    /// Waits until a datagram arrives. Unlike the sync socket, there is no non-blocking mode or read timeout (the call
+    /// can be wrapped in a timeout of the runtime instead).
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        self.core.release_held();
+        loop {
+            let datagram = self.receiver.recv().await?;
+
+            if self.core.accepts(&datagram) {
+                return Ok((datagram.copy_to(buf), datagram.from));
+            }
+        }
+    }
+
+    /// Receives a datagram from the connected peer. The datagram is truncated if the buffer is too small.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.core.peer_addr()?;
+        self.recv_from(buf).await.map(|(len, _)| len)
+    }
+}
+
+impl DatagramSender for Sender<Datagram> {
+    fn send_datagram(&self, datagram: Datagram) {
+        let _ = self.try_send(datagram);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{error::ErrorKind, futures::MockStream};
+
+    #[test]
+    fn check_udp_communication() {
+        async_std::task::block_on(async {
+            let network = MockNetwork::new();
+            let (stream, _) = MockStream::pair();
+
+            let server = MockUdpSocket::bind(&network, "0.0.0.0:0".parse().unwrap()).unwrap();
+            let client = MockUdpSocket::bind(&network, "127.0.0.1:0".parse().unwrap()).unwrap();
+            let server_addr = SocketAddr::new(
+                "127.0.0.1".parse().unwrap(),
+                server.local_addr().unwrap().port(),
+            );
+            let client_addr = client.local_addr().unwrap();
+            assert_ne!(stream.local_addr().unwrap().port(), client_addr.port());
+
+            let error = client.send(&[1]).await.unwrap_err();
+            assert_eq!(ErrorKind::NotConnected, error.kind());
+
+            client.connect(server_addr).unwrap();
+            assert_eq!(3, client.send(&[1, 2, 3]).await.unwrap());
+
+            let mut buf = [0; 2];
+            assert_eq!((2, client_addr), server.recv_from(&mut buf).await.unwrap());
+            assert_eq!([1, 2], buf);
+
+            // Datagrams from any other address than the connected peer are discarded
+            let other = MockUdpSocket::bind(&network, "127.0.0.1:0".parse().unwrap()).unwrap();
+            other.send_to(&[4], client_addr).await.unwrap();
+
+            server.set_faults(DatagramFaults::new().duplication(1.0));
+            server.send_to(&[5], client_addr).await.unwrap();
+
+            let mut buf = [0; 8];
+            assert_eq!(1, client.recv(&mut buf).await.unwrap());
+            assert_eq!(5, buf[0]);
+            assert_eq!(1, client.recv(&mut buf).await.unwrap());
+            assert_eq!(5, buf[0]);
+        });
+    }
+}
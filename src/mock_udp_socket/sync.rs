@@ -0,0 +1,263 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    datagram::{Datagram, DatagramSender, Registry, SocketCore},
+    error::Error,
+    mock_stream::sync::check_timeout,
+    primitives::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex, MutexGuard,
+    },
+    sync::DatagramFaults,
+};
+
+/// Network of synchronous mock datagram sockets
+///
+/// Sockets bound to the same network share an address space and can send datagrams to each other.
+///
+/// Each network is a separate address space: addresses of mock streams and listeners are not bound in any network and
+/// sockets of different backends can not be bound to the same network. Ports allocated for sockets bound to port `0`
+/// are taken from the ones assigned to mock streams created without explicit addresses though, so they never collide.
+#[derive(Debug, Clone, Default)]
+pub struct MockNetwork {
+    registry: Arc<Registry<Sender<Datagram>>>,
+}
+
+impl MockNetwork {
+    /// Creates a new mock network
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+/// Synchronous mock datagram socket
+#[derive(Debug)]
+pub struct MockUdpSocket {
+    core: SocketCore<Sender<Datagram>>,
+    receiver: Mutex<Receiver<Datagram>>,
+    blocking: Mutex<Blocking>,
+}
+
+/// Blocking mode and read timeout of a mock datagram socket (sends never block)
+#[derive(Debug, Default)]
+struct Blocking {
+    nonblocking: bool,
+    read_timeout: Option<Duration>,
+}
+
+impl MockUdpSocket {
+    /// Binds a new socket to given address on the mock network. A free port is allocated if the port is `0`.
+    pub fn bind(network: &MockNetwork, addr: SocketAddr) -> Result<Self, Error> {
+        let (sender, receiver) = channel();
+
+        Ok(Self {
+            core: SocketCore::bind(&network.registry, addr, sender)?,
+            receiver: Mutex::new(receiver),
+            blocking: Default::default(),
+        })
+    }
+
+    /// Returns the address this socket is bound to
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.core.local_addr())
+    }
+
+    /// Returns the address of the peer this socket is connected to
+    pub fn peer_addr(&self) -> Result<SocketAddr, Error> {
+        self.core.peer_addr().map_err(Into::into)
+    }
+
+    /// Connects this socket to given address, so that `send` and `recv` can be used. Datagrams from any other address
+    /// are discarded.
+    pub fn connect(&self, addr: SocketAddr) -> Result<(), Error> {
+        self.core.connect(addr);
+        Ok(())
+    }
+
+    /// Moves this socket into or out of non-blocking mode
+    ///
+    /// In non-blocking mode, `recv_from` and `recv` return an error of kind `WouldBlock` instead of blocking when no
+    /// datagram is available. Sends never block in either mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), Error> {
+        self.lock_blocking().nonblocking = nonblocking;
+        Ok(())
+    }
+
+    /// Sets the read timeout of this socket (`None` means that `recv_from` and `recv` block indefinitely)
+    ///
+    /// Returns an error if a zero duration is passed.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.lock_blocking().read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the read timeout of this socket
+    pub fn read_timeout(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.lock_blocking().read_timeout)
+    }
+
+    /// Sets the faults injected into the datagrams sent by this socket
+    pub fn set_faults(&self, faults: DatagramFaults) {
+        self.core.set_faults(faults)
+    }
+
+    /// Sends a datagram to given address. Datagrams sent to an address without any socket are silently dropped.
+    pub fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize, Error> {
+        Ok(self.core.send_to(buf, addr))
+    }
+
+    /// Sends a datagram to the connected peer
+    pub fn send(&self, buf: &[u8]) -> Result<usize, Error> {
+        self.core.send(buf).map_err(Into::into)
+    }
+
+    /// Receives a datagram along with the address of its sender. The datagram is truncated if the buffer is too small.
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), Error> {
+        let (nonblocking, read_timeout) = {
+            let blocking = self.lock_blocking();
+            (blocking.nonblocking, blocking.read_timeout)
+        };
+        let deadline = read_timeout.map(|timeout| Instant::now() + timeout);
+
+        let receiver = self.receiver.lock().unwrap_or_else(PoisonError::into_inner);
+        self.core.release_held();
+
+        loop {
+            let datagram = match deadline {
+                _ if nonblocking => receiver.try_recv()?,
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))?
+                }
+                None => receiver.recv()?,
+            };
+
+            if self.core.accepts(&datagram) {
+                return Ok((datagram.copy_to(buf), datagram.from));
+            }
+        }
+    }
+
+    /// Receives a datagram from the connected peer. The datagram is truncated if the buffer is too small.
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.core.peer_addr()?;
+        self.recv_from(buf).map(|(len, _)| len)
+    }
+
+    fn lock_blocking(&self) -> MutexGuard<'_, Blocking> {
+        self.blocking.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl DatagramSender for Sender<Datagram> {
+    fn send_datagram(&self, datagram: Datagram) {
+        let _ = self.send(datagram);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn check_udp_communication() {
+        let network = MockNetwork::new();
+
+        let server = MockUdpSocket::bind(&network, "127.0.0.1:53".parse().unwrap()).unwrap();
+        let client = MockUdpSocket::bind(&network, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        let error = MockUdpSocket::bind(&network, "127.0.0.1:53".parse().unwrap()).unwrap_err();
+        assert_eq!(ErrorKind::AddrInUse, error.kind());
+
+        let error = client.send(&[1]).unwrap_err();
+        assert_eq!(ErrorKind::NotConnected, error.kind());
+
+        client.connect(server.local_addr().unwrap()).unwrap();
+        assert_eq!(3, client.send(&[1, 2, 3]).unwrap());
+
+        let mut buf = [0; 2];
+        assert_eq!((2, client_addr), server.recv_from(&mut buf).unwrap());
+        assert_eq!([1, 2], buf);
+
+        server.set_faults(DatagramFaults::new().duplication(1.0));
+        server.send_to(&[4], client_addr).unwrap();
+
+        let mut buf = [0; 8];
+        assert_eq!(1, client.recv(&mut buf).unwrap());
+        assert_eq!(1, client.recv(&mut buf).unwrap());
+
+        server.set_faults(DatagramFaults::new().loss(1.0));
+        server.send_to(&[5], client_addr).unwrap();
+        server.set_faults(DatagramFaults::new());
+        server.send_to(&[6], client_addr).unwrap();
+
+        assert_eq!(1, client.recv(&mut buf).unwrap());
+        assert_eq!(6, buf[0]);
+    }
+
+    #[test]
+    fn check_reordering_and_wildcard() {
+        let network = MockNetwork::new();
+
+        let server = MockUdpSocket::bind(&network, "0.0.0.0:53".parse().unwrap()).unwrap();
+        let client = MockUdpSocket::bind(&network, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let server_addr = "127.0.0.1:53".parse().unwrap();
+        let mut buf = [0; 8];
+
+        // Single datagram held for reordering is released when the server receives
+        client.set_faults(DatagramFaults::new().reordering(1.0));
+        client.send_to(&[1], server_addr).unwrap();
+        assert_eq!(1, server.recv_from(&mut buf).unwrap().0);
+        assert_eq!(1, buf[0]);
+
+        client.send_to(&[2], server_addr).unwrap();
+        client.send_to(&[3], server_addr).unwrap();
+        assert_eq!(1, server.recv_from(&mut buf).unwrap().0);
+        assert_eq!(3, buf[0]);
+        assert_eq!(1, server.recv_from(&mut buf).unwrap().0);
+        assert_eq!(2, buf[0]);
+
+        // Datagram held by a dropped socket is delivered
+        client.send_to(&[4], server_addr).unwrap();
+        drop(client);
+        assert_eq!(1, server.recv_from(&mut buf).unwrap().0);
+        assert_eq!(4, buf[0]);
+    }
+
+    #[test]
+    fn check_blocking_modes() {
+        let network = MockNetwork::new();
+
+        let socket = MockUdpSocket::bind(&network, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let mut buf = [0; 8];
+
+        let error = socket.set_read_timeout(Some(Duration::ZERO)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+
+        socket
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(10)),
+            socket.read_timeout().unwrap()
+        );
+        let error = socket.recv_from(&mut buf).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ));
+
+        socket.set_nonblocking(true).unwrap();
+        let error = socket.recv_from(&mut buf).unwrap_err();
+        assert_eq!(ErrorKind::WouldBlock, error.kind());
+
+        socket.send_to(&[1], socket.local_addr().unwrap()).unwrap();
+        assert_eq!(1, socket.recv_from(&mut buf).unwrap().0);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{fmt, path::Path};
+
+use crate::{
+    error::{Error, Inner},
+    primitives::mpsc::{channel, Receiver, Sender},
+    sync::{MockUnixStream, UnixSocketAddr},
+};
+
+/// Synchronous mock unix domain listener
+pub struct MockUnixListener {
+    receiver: Receiver<MockUnixStream>,
+    addr: UnixSocketAddr,
+}
+
+impl MockUnixListener {
+    /// Creates a new synchronous mock unix domain listener bound to given path (nothing is created on filesystem)
+    pub fn bind(path: impl AsRef<Path>) -> (Self, UnixHandle) {
+        let (sender, receiver) = channel();
+        let addr = UnixSocketAddr::from_path(path);
+
+        (
+            Self {
+                receiver,
+                addr: addr.clone(),
+            },
+            UnixHandle { sender, addr },
+        )
+    }
+
+    /// Accept a new connection. Returns a mock unix domain stream supplied by the sender
+    pub fn accept(&self) -> Result<MockUnixStream, Error> {
+        self.receiver.recv().map_err(Into::into)
+    }
+
+    /// Returns the address this listener is bound to
+    pub fn local_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.addr.clone())
+    }
+}
+
+impl fmt::Debug for MockUnixListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockUnixListener")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle for synchronous mock unix domain listener used to connect to the listener
+pub struct UnixHandle {
+    sender: Sender<MockUnixStream>,
+    addr: UnixSocketAddr,
+}
+
+impl UnixHandle {
+    pub(crate) fn addr(&self) -> UnixSocketAddr {
+        self.addr.clone()
+    }
+
+    pub(crate) fn send(&self, stream: MockUnixStream) -> Result<(), Error> {
+        self.sender
+            .send(stream)
+            .map_err(|_| Inner::SyncConnectError.into())
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use crate::{
+    error::{Error, Inner},
+    primitives::unbounded::{
+        unbounded_channel as unbounded, UnboundedReceiver as Receiver, UnboundedSender as Sender,
+    },
+    tokio::{MockUnixStream, UnixSocketAddr},
+};
+
+#[derive(Debug)]
+/// Asynchronous mock unix domain listener
+pub struct MockUnixListener {
+    receiver: Receiver<MockUnixStream>,
+    addr: UnixSocketAddr,
+}
+
+impl MockUnixListener {
+    /// Creates a new asynchronous mock unix domain listener bound to given path (nothing is created on filesystem)
+    pub fn bind(path: impl AsRef<Path>) -> (Self, UnixHandle) {
+        let (sender, receiver) = unbounded();
+        let addr = UnixSocketAddr::from_path(path);
+
+        (
+            Self {
+                receiver,
+                addr: addr.clone(),
+            },
+            UnixHandle { sender, addr },
+        )
+    }
+
+    /// Accept a new connection. Returns a mock unix domain stream supplied by the sender
+    pub async fn accept(&mut self) -> Result<MockUnixStream, Error> {
+        self.receiver
+            .recv()
+            .await
+            .ok_or(Inner::TokioRecvError)
+            .map_err(Into::into)
+    }
+
+    /// Returns the address this listener is bound to
+    pub fn local_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.addr.clone())
+    }
+}
+
+/// Handle for asynchronous mock unix domain listener used to connect to the listener
+pub struct UnixHandle {
+    sender: Sender<MockUnixStream>,
+    addr: UnixSocketAddr,
+}
+
+impl UnixHandle {
+    pub(crate) fn addr(&self) -> UnixSocketAddr {
+        self.addr.clone()
+    }
+
+    pub(crate) fn send(&self, stream: MockUnixStream) -> Result<(), Error> {
+        self.sender
+            .send(stream)
+            .map_err(|_| Inner::TokioConnectError.into())
+    }
+}
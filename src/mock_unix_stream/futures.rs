@@ -0,0 +1,141 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    error::Error,
+    futures::{Ancillary, MockStream, UnixHandle, UnixSocketAddr},
+    unix::AncillaryState,
+};
+
+/// Asynchronous mock unix domain stream
+#[derive(Debug)]
+pub struct MockUnixStream {
+    stream: MockStream,
+    ancillary: AncillaryState,
+    local_addr: UnixSocketAddr,
+    peer_addr: UnixSocketAddr,
+}
+
+impl MockUnixStream {
+    /// Connects to a mock unix domain listener
+    pub async fn connect(handle: &UnixHandle) -> Result<Self, Error> {
+        let (stream_1, stream_2) = Self::pair_with_addrs(UnixSocketAddr::unnamed(), handle.addr());
+        handle.send(stream_2).await?;
+        Ok(stream_1)
+    }
+
+    /// Creates a pair of connected unnamed mock unix domain streams
+    pub fn pair() -> (Self, Self) {
+        Self::pair_with_addrs(UnixSocketAddr::unnamed(), UnixSocketAddr::unnamed())
+    }
+
+    fn pair_with_addrs(addr_1: UnixSocketAddr, addr_2: UnixSocketAddr) -> (Self, Self) {
+        let (stream_1, stream_2) = MockStream::pair();
+        let (ancillary_1, ancillary_2) = AncillaryState::pair();
+
+        let stream_1 = Self {
+            stream: stream_1,
+            ancillary: ancillary_1,
+            local_addr: addr_1.clone(),
+            peer_addr: addr_2.clone(),
+        };
+
+        let stream_2 = Self {
+            stream: stream_2,
+            ancillary: ancillary_2,
+            local_addr: addr_2,
+            peer_addr: addr_1,
+        };
+
+        (stream_1, stream_2)
+    }
+
+    /// Returns the address of this end of the stream
+    pub fn local_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.local_addr.clone())
+    }
+
+    /// Returns the address of the other end of the stream
+    pub fn peer_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.peer_addr.clone())
+    }
+
+    /// Returns the underlying mock stream
+    pub fn get_ref(&self) -> &MockStream {
+        &self.stream
+    }
+
+    /// Sends bytes along with ancillary data attached to them
+    pub async fn send_with_ancillary(
+        &mut self,
+        buf: &[u8],
+        ancillary: Vec<Ancillary>,
+    ) -> Result<usize, Error> {
+        // Attached before sending, so that the peer can not read the bytes before the ancillary data is attached
+        self.ancillary.attach(ancillary);
+        let result = self.send(buf);
+
+        if result.is_err() {
+            self.ancillary.detach();
+        }
+
+        result
+    }
+
+    /// Receives bytes along with the ancillary data attached to them
+    ///
+    /// Ancillary data attached to bytes which were read using `AsyncRead` is discarded.
+    pub async fn recv_with_ancillary(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, Vec<Ancillary>), Error> {
+        let len = self.stream.receive(buf).await?;
+        Ok((len, self.ancillary.record_read(len)))
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let len = self.stream.send(buf)?;
+        self.ancillary.record_write(len);
+        Ok(len)
+    }
+}
+
+impl AsyncRead for MockUnixStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.stream).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(len)) = result {
+            this.ancillary.record_read(len);
+        }
+
+        result
+    }
+}
+
+impl AsyncWrite for MockUnixStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().send(buf).map_err(Into::into))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    error::Error,
+    sync::{Ancillary, MockStream, UnixHandle, UnixSocketAddr},
+    unix::AncillaryState,
+};
+
+/// Synchronous mock unix domain stream
+pub struct MockUnixStream {
+    stream: MockStream,
+    ancillary: AncillaryState,
+    local_addr: UnixSocketAddr,
+    peer_addr: UnixSocketAddr,
+}
+
+impl MockUnixStream {
+    /// Connects to a mock unix domain listener
+    pub fn connect(handle: &UnixHandle) -> Result<Self, Error> {
+        let (stream_1, stream_2) = Self::pair_with_addrs(UnixSocketAddr::unnamed(), handle.addr());
+        handle.send(stream_2)?;
+        Ok(stream_1)
+    }
+
+    /// Creates a pair of connected unnamed mock unix domain streams
+    pub fn pair() -> (Self, Self) {
+        Self::pair_with_addrs(UnixSocketAddr::unnamed(), UnixSocketAddr::unnamed())
+    }
+
+    fn pair_with_addrs(addr_1: UnixSocketAddr, addr_2: UnixSocketAddr) -> (Self, Self) {
+        let (stream_1, stream_2) = MockStream::pair();
+        let (ancillary_1, ancillary_2) = AncillaryState::pair();
+
+        let stream_1 = Self {
+            stream: stream_1,
+            ancillary: ancillary_1,
+            local_addr: addr_1.clone(),
+            peer_addr: addr_2.clone(),
+        };
+
+        let stream_2 = Self {
+            stream: stream_2,
+            ancillary: ancillary_2,
+            local_addr: addr_2,
+            peer_addr: addr_1,
+        };
+
+        (stream_1, stream_2)
+    }
+
+    /// Returns the address of this end of the stream
+    pub fn local_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.local_addr.clone())
+    }
+
+    /// Returns the address of the other end of the stream
+    pub fn peer_addr(&self) -> Result<UnixSocketAddr, Error> {
+        Ok(self.peer_addr.clone())
+    }
+
+    /// Returns the underlying mock stream
+    pub fn get_ref(&self) -> &MockStream {
+        &self.stream
+    }
+
+    /// Sends bytes along with ancillary data attached to them
+    pub fn send_with_ancillary(
+        &mut self,
+        buf: &[u8],
+        ancillary: Vec<Ancillary>,
+    ) -> Result<usize, Error> {
+        // Attached before sending, so that the peer can not read the bytes before the ancillary data is attached
+        self.ancillary.attach(ancillary);
+        let result = self.send(buf);
+
+        if result.is_err() {
+            self.ancillary.detach();
+        }
+
+        result
+    }
+
+    /// Receives bytes along with the ancillary data attached to them
+    ///
+    /// Ancillary data attached to bytes which were read using `Read` is discarded.
+    pub fn recv_with_ancillary(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<(usize, Vec<Ancillary>), Error> {
+        let len = self.stream.receive(buf)?;
+        Ok((len, self.ancillary.record_read(len)))
+    }
+
+    fn send(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let len = self.stream.send(buf)?;
+        self.ancillary.record_write(len);
+        Ok(len)
+    }
+}
+
+impl Read for MockUnixStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_ancillary(buf)
+            .map(|(len, _)| len)
+            .map_err(Into::into)
+    }
+}
+
+impl Write for MockUnixStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send(buf).map_err(Into::into)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sync::{Credentials, MockUnixListener};
+
+    #[test]
+    fn check_ancillary_data() {
+        let (listener, handle) = MockUnixListener::bind("/run/agent.sock");
+
+        let mut client = MockUnixStream::connect(&handle).unwrap();
+        let mut server = listener.accept().unwrap();
+
+        assert!(client.local_addr().unwrap().is_unnamed());
+        assert_eq!(
+            Some("/run/agent.sock".as_ref()),
+            client.peer_addr().unwrap().as_pathname()
+        );
+
+        let credentials = Credentials {
+            pid: 1,
+            uid: 1000,
+            gid: 1000,
+        };
+
+        client.write_all(&[1, 2]).unwrap();
+        client
+            .send_with_ancillary(&[3], vec![Ancillary::Credentials(credentials)])
+            .unwrap();
+        client
+            .send_with_ancillary(&[4], vec![Ancillary::Rights(vec![3, 4])])
+            .unwrap();
+
+        let mut buf = [0; 2];
+        assert_eq!((2, vec![]), server.recv_with_ancillary(&mut buf).unwrap());
+
+        let mut buf = [0; 1];
+        server.read_exact(&mut buf).unwrap();
+
+        assert_eq!(
+            (1, vec![Ancillary::Rights(vec![3, 4])]),
+            server.recv_with_ancillary(&mut buf).unwrap()
+        );
+    }
+}
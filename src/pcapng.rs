@@ -0,0 +1,334 @@
+//! Export of recorded mock traffic as pcapng captures with synthetic Ethernet, IP and TCP headers
+use std::{
+    convert::TryFrom,
+    io,
+    net::{IpAddr, SocketAddr},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::recording::RecordedEvent;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const LINKTYPE_ETHERNET: u16 = 1;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const IPPROTO_TCP: u8 = 6;
+
+const FIN: u8 = 0x01;
+const SYN: u8 = 0x02;
+const RST: u8 = 0x04;
+const PSH: u8 = 0x08;
+const ACK: u8 = 0x10;
+
+/// Maximum payload of a synthetic TCP segment (larger writes are split into multiple segments)
+const MSS: usize = 1460;
+
+/// Writes a pcapng capture of the events recorded on a connection between `local_addr` (client) and `peer_addr`
+/// (server). The capture starts with a TCP handshake at `started_at`.
+pub(crate) fn write(
+    mut writer: impl io::Write,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    started_at: SystemTime,
+    events: &[RecordedEvent],
+) -> io::Result<()> {
+    let mut section_header = Vec::new();
+    section_header.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+    section_header.extend_from_slice(&1u16.to_le_bytes());
+    section_header.extend_from_slice(&0u16.to_le_bytes());
+    section_header.extend_from_slice(&(-1i64).to_le_bytes());
+    write_block(&mut writer, SECTION_HEADER_BLOCK, &section_header)?;
+
+    let mut interface_description = Vec::new();
+    interface_description.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    interface_description.extend_from_slice(&0u16.to_le_bytes());
+    interface_description.extend_from_slice(&0u32.to_le_bytes());
+    write_block(
+        &mut writer,
+        INTERFACE_DESCRIPTION_BLOCK,
+        &interface_description,
+    )?;
+
+    let mut capture = Capture {
+        writer,
+        endpoints: [Endpoint::new(local_addr, 1), Endpoint::new(peer_addr, 2)],
+        ip_id: 0,
+    };
+
+    capture.segment(started_at, 0, SYN, &[])?;
+    capture.segment(started_at, 1, SYN | ACK, &[])?;
+    capture.segment(started_at, 0, ACK, &[])?;
+
+    for event in events {
+        match event {
+            RecordedEvent::Data {
+                from, bytes, at, ..
+            } => {
+                let side = capture.side(*from);
+
+                for chunk in bytes.chunks(MSS) {
+                    capture.segment(*at, side, PSH | ACK, chunk)?;
+                }
+            }
+            RecordedEvent::Shutdown { from, at, .. } => {
+                let side = capture.side(*from);
+                capture.segment(*at, side, FIN | ACK, &[])?;
+            }
+            RecordedEvent::Reset { from, at, .. } => {
+                let side = capture.side(*from);
+                capture.segment(*at, side, RST | ACK, &[])?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// One end of the synthetic TCP connection
+struct Endpoint {
+    addr: SocketAddr,
+    mac: [u8; 6],
+    seq: u32,
+}
+
+impl Endpoint {
+    fn new(addr: SocketAddr, id: u8) -> Self {
+        Self {
+            addr,
+            mac: [0x02, 0, 0, 0, 0, id],
+            seq: u32::from(id) * 1_000_000,
+        }
+    }
+}
+
+struct Capture<W> {
+    writer: W,
+    endpoints: [Endpoint; 2],
+    ip_id: u16,
+}
+
+impl<W: io::Write> Capture<W> {
+    /// Returns the index of the endpoint with given address
+    fn side(&self, addr: SocketAddr) -> usize {
+        if addr == self.endpoints[0].addr {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Writes a TCP segment sent by the endpoint at index `side`
+    fn segment(
+        &mut self,
+        at: SystemTime,
+        side: usize,
+        flags: u8,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        let (src, dst) = (&self.endpoints[side], &self.endpoints[1 - side]);
+        let ack = if flags & ACK != 0 { dst.seq } else { 0 };
+
+        let mut tcp = Vec::with_capacity(20 + payload.len());
+        tcp.extend_from_slice(&src.addr.port().to_be_bytes());
+        tcp.extend_from_slice(&dst.addr.port().to_be_bytes());
+        tcp.extend_from_slice(&src.seq.to_be_bytes());
+        tcp.extend_from_slice(&ack.to_be_bytes());
+        tcp.push(5 << 4);
+        tcp.push(flags);
+        tcp.extend_from_slice(&u16::MAX.to_be_bytes());
+        tcp.extend_from_slice(&[0, 0, 0, 0]);
+        tcp.extend_from_slice(payload);
+
+        let mut frame = Vec::with_capacity(54 + tcp.len());
+        frame.extend_from_slice(&dst.mac);
+        frame.extend_from_slice(&src.mac);
+
+        match (src.addr.ip(), dst.addr.ip()) {
+            (IpAddr::V4(src_ip), IpAddr::V4(dst_ip)) => {
+                let mut pseudo_header = Vec::with_capacity(12 + tcp.len());
+                pseudo_header.extend_from_slice(&src_ip.octets());
+                pseudo_header.extend_from_slice(&dst_ip.octets());
+                pseudo_header.extend_from_slice(&[0, IPPROTO_TCP]);
+                pseudo_header.extend_from_slice(&length(tcp.len())?.to_be_bytes());
+                pseudo_header.extend_from_slice(&tcp);
+                tcp[16..18].copy_from_slice(&checksum(&pseudo_header).to_be_bytes());
+
+                let mut ip = Vec::with_capacity(20);
+                ip.extend_from_slice(&[0x45, 0]);
+                ip.extend_from_slice(&length(20 + tcp.len())?.to_be_bytes());
+                ip.extend_from_slice(&self.ip_id.to_be_bytes());
+                ip.extend_from_slice(&[0x40, 0, 64, IPPROTO_TCP, 0, 0]);
+                ip.extend_from_slice(&src_ip.octets());
+                ip.extend_from_slice(&dst_ip.octets());
+                let ip_checksum = checksum(&ip);
+                ip[10..12].copy_from_slice(&ip_checksum.to_be_bytes());
+
+                self.ip_id = self.ip_id.wrapping_add(1);
+
+                frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+                frame.extend_from_slice(&ip);
+            }
+            (src_ip, dst_ip) => {
+                let src_ip = to_ipv6(src_ip);
+                let dst_ip = to_ipv6(dst_ip);
+
+                let mut pseudo_header = Vec::with_capacity(40 + tcp.len());
+                pseudo_header.extend_from_slice(&src_ip);
+                pseudo_header.extend_from_slice(&dst_ip);
+                pseudo_header.extend_from_slice(&(u32::from(length(tcp.len())?)).to_be_bytes());
+                pseudo_header.extend_from_slice(&[0, 0, 0, IPPROTO_TCP]);
+                pseudo_header.extend_from_slice(&tcp);
+                tcp[16..18].copy_from_slice(&checksum(&pseudo_header).to_be_bytes());
+
+                frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+                frame.extend_from_slice(&[0x60, 0, 0, 0]);
+                frame.extend_from_slice(&length(tcp.len())?.to_be_bytes());
+                frame.extend_from_slice(&[IPPROTO_TCP, 64]);
+                frame.extend_from_slice(&src_ip);
+                frame.extend_from_slice(&dst_ip);
+            }
+        }
+
+        frame.extend_from_slice(&tcp);
+
+        let consumed = payload.len() + usize::from(flags & (SYN | FIN) != 0);
+        let src = &mut self.endpoints[side];
+        src.seq = src.seq.wrapping_add(consumed as u32);
+
+        let micros = at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let frame_len = u32::try_from(frame.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "frame is too large"))?;
+
+        let mut packet = Vec::with_capacity(20 + frame.len());
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        packet.extend_from_slice(&(micros as u32).to_le_bytes());
+        packet.extend_from_slice(&frame_len.to_le_bytes());
+        packet.extend_from_slice(&frame_len.to_le_bytes());
+        packet.extend_from_slice(&frame);
+
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &packet)
+    }
+}
+
+/// Writes a pcapng block with given type and body (padded to 32 bits)
+fn write_block(writer: &mut impl io::Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = u32::try_from(12 + body.len() + padding)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "block is too large"))?;
+
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&total_len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&[0; 3][..padding])?;
+    writer.write_all(&total_len.to_le_bytes())
+}
+
+/// Computes the internet checksum (RFC 1071) of given bytes
+fn checksum(bytes: &[u8]) -> u16 {
+    let mut sum = bytes
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum::<u32>();
+
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+fn length(len: usize) -> io::Result<u16> {
+    u16::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "segment is too large"))
+}
+
+fn to_ipv6(ip: IpAddr) -> [u8; 16] {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+        IpAddr::V6(ip) => ip.octets(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_capture_layout() {
+        let client: SocketAddr = "10.0.0.1:49152".parse().unwrap();
+        let server: SocketAddr = "10.0.0.2:80".parse().unwrap();
+        let at = SystemTime::now();
+
+        let events = vec![
+            RecordedEvent::Data {
+                from: client,
+                to: server,
+                bytes: vec![1; 2000],
+                at,
+            },
+            RecordedEvent::Shutdown {
+                from: server,
+                to: client,
+                at,
+            },
+            RecordedEvent::Reset {
+                from: server,
+                to: client,
+                at,
+            },
+        ];
+
+        let mut capture = Vec::new();
+        write(&mut capture, client, server, at, &events).unwrap();
+
+        let mut blocks = Vec::new();
+        let mut rest = capture.as_slice();
+
+        while !rest.is_empty() {
+            let block_type = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            blocks.push((block_type, &rest[8..len - 4]));
+            rest = &rest[len..];
+        }
+
+        // Section header, interface description, handshake, two data segments, FIN and RST
+        assert_eq!(9, blocks.len());
+        assert_eq!(SECTION_HEADER_BLOCK, blocks[0].0);
+        assert_eq!(INTERFACE_DESCRIPTION_BLOCK, blocks[1].0);
+
+        let flags = blocks[2..]
+            .iter()
+            .map(|(_, body)| {
+                let ip = &body[20 + 14..];
+                assert_eq!(0, checksum(&ip[..20]));
+                ip[20 + 13]
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                SYN,
+                SYN | ACK,
+                ACK,
+                PSH | ACK,
+                PSH | ACK,
+                FIN | ACK,
+                RST | ACK
+            ],
+            flags
+        );
+
+        // RST is sent by the server, which reset the connection
+        let tcp = &blocks[8].1[20 + 14 + 20..];
+        assert_eq!(server.port(), u16::from_be_bytes([tcp[0], tcp[1]]));
+    }
+}
@@ -0,0 +1,27 @@
+//! Ready-made behaviors of mock peers
+use std::time::Duration;
+
+/// Size of the buffer used by mock peers for reading data (and the number of unread bytes up to which a repeating
+/// peer keeps writing)
+pub(crate) const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Interval at which a repeating peer checks whether its unread data has been consumed (or the stream is closed)
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Behavior of a mock peer running on its own thread (or task) against the other side of a connection
+///
+/// Every peer stops when the stream under test is dropped (or the connection is reset).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Peer {
+    /// Writes all the data read back to the stream
+    Echo,
+    /// Reads and drops all the data
+    Sink,
+    /// Writes given bytes and closes the connection
+    Source(Vec<u8>),
+    /// Writes given pattern over and over again (as fast as the stream reads it) while dropping all the data read
+    Repeat(Vec<u8>),
+    /// Closes the connection right away
+    Discard,
+}
@@ -0,0 +1,128 @@
+//! `no_std` compatible pipes carrying the data in one direction of the connection between a pair of embedded mock
+//! IO streams (only depends on `alloc` and a spin lock)
+use alloc::{collections::VecDeque, sync::Arc};
+use core::task::{Context, Poll, Waker};
+
+use spin::Mutex;
+
+/// Error returned when reading from or writing to a pipe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PipeError {
+    /// The other end of the pipe has been dropped
+    Closed,
+    /// The connection has been reset
+    Reset,
+}
+
+/// One direction of the connection between a pair of embedded mock IO streams
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Pipe {
+    inner: Arc<Mutex<PipeState>>,
+}
+
+#[derive(Debug, Default)]
+struct PipeState {
+    bytes: VecDeque<u8>,
+    closed: bool,
+    reset: bool,
+    reader: Option<Waker>,
+}
+
+impl Pipe {
+    /// Appends bytes to the pipe (writes never block, so the pipe can grow without any limit)
+    pub(crate) fn write(&self, bytes: &[u8]) -> Result<usize, PipeError> {
+        let mut state = self.inner.lock();
+
+        if state.reset {
+            return Err(PipeError::Reset);
+        }
+
+        if state.closed {
+            return Err(PipeError::Closed);
+        }
+
+        state.bytes.extend(bytes);
+        state.wake();
+
+        Ok(bytes.len())
+    }
+
+    /// Moves bytes from the pipe into the buffer. Returns `None` if there are no bytes to read yet and the pipe is
+    /// still open. Reads return zero bytes after the pipe is closed and all the bytes have been read (i.e., on EOF).
+    pub(crate) fn try_read(&self, buf: &mut [u8]) -> Option<Result<usize, PipeError>> {
+        self.inner.lock().read(buf)
+    }
+
+    /// Polls for reading bytes from the pipe (the task is woken when bytes are written or the pipe is closed or reset)
+    pub(crate) fn poll_read(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, PipeError>> {
+        let mut state = self.inner.lock();
+
+        match state.read(buf) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.reader = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns `true` if a read does not block
+    pub(crate) fn is_ready(&self) -> bool {
+        let state = self.inner.lock();
+        !state.bytes.is_empty() || state.closed || state.reset
+    }
+
+    /// Closes the pipe
+    pub(crate) fn close(&self) {
+        let mut state = self.inner.lock();
+        state.closed = true;
+        state.wake();
+    }
+
+    /// Resets the pipe (all the bytes not read yet are lost)
+    pub(crate) fn reset(&self) {
+        let mut state = self.inner.lock();
+        state.reset = true;
+        state.bytes.clear();
+        state.wake();
+    }
+
+    /// Returns `true` if the pipe has been reset
+    pub(crate) fn is_reset(&self) -> bool {
+        self.inner.lock().reset
+    }
+}
+
+impl PipeState {
+    fn read(&mut self, buf: &mut [u8]) -> Option<Result<usize, PipeError>> {
+        if self.reset {
+            return Some(Err(PipeError::Reset));
+        }
+
+        if buf.is_empty() || (self.bytes.is_empty() && self.closed) {
+            return Some(Ok(0));
+        }
+
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let len = buf.len().min(self.bytes.len());
+
+        for (slot, byte) in buf.iter_mut().zip(self.bytes.drain(..len)) {
+            *slot = byte;
+        }
+
+        Some(Ok(len))
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.reader.take() {
+            waker.wake();
+        }
+    }
+}
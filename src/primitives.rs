@@ -0,0 +1,351 @@
+//! Synchronization primitives used internally by mock IO streams and listeners
+//!
+//! When compiled with `--cfg loom`, locks, atomics and channels are replaced by the primitives of `loom` so that the
+//! handshakes between mock streams and listeners can be model checked. Reference counting (`Arc` and `Weak`) always
+//! uses the standard library because `loom` does not provide weak references.
+#[cfg(loom)]
+pub(crate) use loom::sync::{atomic, Mutex, MutexGuard};
+#[cfg(all(not(loom), feature = "sync"))]
+pub(crate) use std::sync::mpsc;
+#[cfg(not(loom))]
+pub(crate) use std::sync::{atomic, Mutex, MutexGuard};
+#[cfg(all(not(loom), feature = "async-tokio"))]
+pub(crate) use tokio::sync::mpsc as unbounded;
+
+/// Channel built on the lock and condition variable of `loom`
+///
+/// Channels of `loom` never observe disconnection (a receive on a channel whose senders are all dropped blocks
+/// forever), so this one tracks both ends to let models cover peers going away. `loom` has no notion of time, so
+/// `recv_timeout` waits like `recv`.
+#[cfg(all(loom, feature = "sync"))]
+pub(crate) mod mpsc {
+    use std::{
+        collections::VecDeque,
+        fmt,
+        sync::{
+            mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError},
+            Arc, PoisonError,
+        },
+        time::Duration,
+    };
+
+    use loom::sync::{Condvar, Mutex, MutexGuard};
+
+    struct Shared<T> {
+        state: Mutex<State<T>>,
+        ready: Condvar,
+    }
+
+    struct State<T> {
+        queue: VecDeque<T>,
+        senders: usize,
+        receiver: bool,
+    }
+
+    impl<T> Shared<T> {
+        fn lock(&self) -> MutexGuard<'_, State<T>> {
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    /// Sending end of a channel
+    pub(crate) struct Sender<T> {
+        shared: Arc<Shared<T>>,
+    }
+
+    /// Receiving end of a channel
+    pub(crate) struct Receiver<T> {
+        shared: Arc<Shared<T>>,
+    }
+
+    /// Creates a new unbounded channel
+    pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                senders: 1,
+                receiver: true,
+            }),
+            ready: Condvar::new(),
+        });
+
+        (
+            Sender {
+                shared: shared.clone(),
+            },
+            Receiver { shared },
+        )
+    }
+
+    impl<T> Sender<T> {
+        pub(crate) fn send(&self, value: T) -> Result<(), SendError<T>> {
+            let mut state = self.shared.lock();
+
+            if !state.receiver {
+                return Err(SendError(value));
+            }
+
+            state.queue.push_back(value);
+            self.shared.ready.notify_one();
+            Ok(())
+        }
+    }
+
+    impl<T> Clone for Sender<T> {
+        fn clone(&self) -> Self {
+            self.shared.lock().senders += 1;
+
+            Self {
+                shared: self.shared.clone(),
+            }
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            let mut state = self.shared.lock();
+            state.senders -= 1;
+
+            if state.senders == 0 {
+                self.shared.ready.notify_one();
+            }
+        }
+    }
+
+    impl<T> fmt::Debug for Sender<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Sender").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> Receiver<T> {
+        pub(crate) fn recv(&self) -> Result<T, RecvError> {
+            let mut state = self.shared.lock();
+
+            loop {
+                if let Some(value) = state.queue.pop_front() {
+                    return Ok(value);
+                }
+
+                if state.senders == 0 {
+                    return Err(RecvError);
+                }
+
+                state = self
+                    .shared
+                    .ready
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+            let mut state = self.shared.lock();
+
+            match state.queue.pop_front() {
+                Some(value) => Ok(value),
+                None if state.senders == 0 => Err(TryRecvError::Disconnected),
+                None => Err(TryRecvError::Empty),
+            }
+        }
+
+        pub(crate) fn recv_timeout(&self, _timeout: Duration) -> Result<T, RecvTimeoutError> {
+            self.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        }
+    }
+
+    impl<T> Drop for Receiver<T> {
+        fn drop(&mut self) {
+            let queue = {
+                let mut state = self.shared.lock();
+                state.receiver = false;
+                std::mem::take(&mut state.queue)
+            };
+
+            // Values are dropped without holding the lock as they may own other ends of channels
+            drop(queue);
+        }
+    }
+
+    impl<T> fmt::Debug for Receiver<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Receiver").finish_non_exhaustive()
+        }
+    }
+}
+
+/// Unbounded asynchronous channel built on the lock of `loom` (with the same interface as the unbounded channel of
+/// `tokio`)
+///
+/// The channel of `tokio` is not visible to `loom`, so the `async-tokio` backend uses this one in models. Tasks waiting
+/// on the receiver are woken by senders (or by the last sender going away) like with `tokio`.
+#[cfg(all(loom, feature = "async-tokio"))]
+pub(crate) mod unbounded {
+    use std::{
+        collections::VecDeque,
+        fmt,
+        future::poll_fn,
+        sync::{Arc, PoisonError},
+        task::{Context, Poll, Waker},
+    };
+
+    use loom::sync::{Mutex, MutexGuard};
+
+    pub(crate) use tokio::sync::mpsc::error;
+
+    use self::error::{SendError, TryRecvError};
+
+    struct Shared<T> {
+        state: Mutex<State<T>>,
+    }
+
+    struct State<T> {
+        queue: VecDeque<T>,
+        senders: usize,
+        receiver: bool,
+        waker: Option<Waker>,
+    }
+
+    impl<T> Shared<T> {
+        fn lock(&self) -> MutexGuard<'_, State<T>> {
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    /// Sending end of a channel
+    pub(crate) struct UnboundedSender<T> {
+        shared: Arc<Shared<T>>,
+    }
+
+    /// Receiving end of a channel
+    pub(crate) struct UnboundedReceiver<T> {
+        shared: Arc<Shared<T>>,
+    }
+
+    /// Creates a new unbounded channel
+    pub(crate) fn unbounded_channel<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                senders: 1,
+                receiver: true,
+                waker: None,
+            }),
+        });
+
+        (
+            UnboundedSender {
+                shared: shared.clone(),
+            },
+            UnboundedReceiver { shared },
+        )
+    }
+
+    impl<T> UnboundedSender<T> {
+        pub(crate) fn send(&self, value: T) -> Result<(), SendError<T>> {
+            let waker = {
+                let mut state = self.shared.lock();
+
+                if !state.receiver {
+                    return Err(SendError(value));
+                }
+
+                state.queue.push_back(value);
+                state.waker.take()
+            };
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+
+            Ok(())
+        }
+
+        pub(crate) fn is_closed(&self) -> bool {
+            !self.shared.lock().receiver
+        }
+    }
+
+    impl<T> Clone for UnboundedSender<T> {
+        fn clone(&self) -> Self {
+            self.shared.lock().senders += 1;
+
+            Self {
+                shared: self.shared.clone(),
+            }
+        }
+    }
+
+    impl<T> Drop for UnboundedSender<T> {
+        fn drop(&mut self) {
+            let waker = {
+                let mut state = self.shared.lock();
+                state.senders -= 1;
+
+                match state.senders {
+                    0 => state.waker.take(),
+                    _ => None,
+                }
+            };
+
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    impl<T> fmt::Debug for UnboundedSender<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("UnboundedSender").finish_non_exhaustive()
+        }
+    }
+
+    impl<T> UnboundedReceiver<T> {
+        pub(crate) async fn recv(&mut self) -> Option<T> {
+            poll_fn(|cx| self.poll_recv(cx)).await
+        }
+
+        pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+            let mut state = self.shared.lock();
+
+            match state.queue.pop_front() {
+                Some(value) => Ok(value),
+                None if state.senders == 0 => Err(TryRecvError::Disconnected),
+                None => Err(TryRecvError::Empty),
+            }
+        }
+
+        pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            let mut state = self.shared.lock();
+
+            match state.queue.pop_front() {
+                Some(value) => Poll::Ready(Some(value)),
+                None if state.senders == 0 => Poll::Ready(None),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    impl<T> Drop for UnboundedReceiver<T> {
+        fn drop(&mut self) {
+            let queue = {
+                let mut state = self.shared.lock();
+                state.receiver = false;
+                state.waker = None;
+                std::mem::take(&mut state.queue)
+            };
+
+            // Values are dropped without holding the lock as they may own other ends of channels
+            drop(queue);
+        }
+    }
+
+    impl<T> fmt::Debug for UnboundedReceiver<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("UnboundedReceiver").finish_non_exhaustive()
+        }
+    }
+}
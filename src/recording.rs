@@ -0,0 +1,278 @@
+//! Opt-in recording of the traffic on a connection between a pair of mock IO streams
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, PoisonError},
+    time::SystemTime,
+};
+
+use crate::{
+    lifecycle::Tracked,
+    pcapng,
+    primitives::{Mutex, MutexGuard},
+    timeline::{Side, Timeline},
+};
+
+/// Event recorded on a connection between a pair of mock IO streams
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RecordedEvent {
+    /// Bytes written by one of the streams
+    Data {
+        /// Address of the stream which wrote the bytes
+        from: SocketAddr,
+        /// Address of the stream the bytes were sent to
+        to: SocketAddr,
+        /// Bytes written
+        bytes: Vec<u8>,
+        /// Time of the write
+        at: SystemTime,
+    },
+    /// Write side of one of the streams was closed (i.e., it was shut down or the stream or its write half was dropped)
+    Shutdown {
+        /// Address of the stream which was closed
+        from: SocketAddr,
+        /// Address of the peer of the closed stream
+        to: SocketAddr,
+        /// Time of the shutdown
+        at: SystemTime,
+    },
+    /// Connection was reset using a controller
+    Reset {
+        /// Address of the stream which reset the connection
+        from: SocketAddr,
+        /// Address of the peer of the stream which reset the connection
+        to: SocketAddr,
+        /// Time of the reset
+        at: SystemTime,
+    },
+}
+
+/// Traffic recorded on a connection between a pair of mock IO streams
+///
+/// A recording is started by calling `record` on a mock stream and keeps collecting events until it is dropped (or
+/// another recording is started on the same connection). The stream which started the recording is treated as the
+/// client of the connection.
+///
+/// ```rust
+/// # use std::io::Write;
+/// use mock_io::sync::MockStream;
+///
+/// let (mut client, server) = MockStream::pair();
+/// let recording = client.record();
+///
+/// client.write_all(b"hello").unwrap();
+/// drop(client);
+///
+/// assert_eq!(2, recording.events().len());
+///
+/// let mut file = Vec::new();
+/// recording.write_pcapng(&mut file).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Recording {
+    events: Events,
+    started_at: SystemTime,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl Recording {
+    /// Returns all the events recorded so far
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        lock(&self.events).clone()
+    }
+
+    /// Returns the address of the stream which started the recording
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the address of the peer of the stream which started the recording
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Returns the events recorded so far as a timeline of both directions (for asserting on their order)
+    pub fn timeline(&self) -> Timeline {
+        Timeline::new(
+            self.events(),
+            self.started_at,
+            self.local_addr,
+            self.peer_addr,
+        )
+    }
+
+    /// Writes the recorded traffic as a pcapng capture with synthetic Ethernet, IP and TCP headers
+    ///
+    /// The capture starts with a TCP handshake from the local address to the peer address. Shutdowns are exported as
+    /// `FIN` segments and resets as `RST` segments.
+    pub fn write_pcapng(&self, writer: impl io::Write) -> io::Result<()> {
+        pcapng::write(
+            writer,
+            self.local_addr,
+            self.peer_addr,
+            self.started_at,
+            &self.events(),
+        )
+    }
+}
+
+type Events = Arc<Mutex<Vec<RecordedEvent>>>;
+
+/// Shared slot for the recording of a connection (empty if the connection is not being recorded) along with the
+/// lifecycle log of the listener the connection went through (if any)
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Recorder {
+    slot: Arc<Mutex<Option<Events>>>,
+    tracked: Arc<Mutex<Option<(Tracked, SocketAddr)>>>,
+}
+
+impl Recorder {
+    /// Creates a recorder for a connection between `addr_1` and `addr_2` along with the taps of both directions
+    pub(crate) fn pair(addr_1: SocketAddr, addr_2: SocketAddr) -> (Self, Tap, Tap) {
+        let recorder = Self::default();
+
+        let tap_1 = Tap {
+            recorder: recorder.clone(),
+            from: addr_1,
+            to: addr_2,
+        };
+
+        let tap_2 = Tap {
+            recorder: recorder.clone(),
+            from: addr_2,
+            to: addr_1,
+        };
+
+        (recorder, tap_1, tap_2)
+    }
+
+    /// Starts a new recording of the connection
+    pub(crate) fn start(&self, local_addr: SocketAddr, peer_addr: SocketAddr) -> Recording {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        *lock(&self.slot) = Some(events.clone());
+
+        Recording {
+            events,
+            started_at: SystemTime::now(),
+            local_addr,
+            peer_addr,
+        }
+    }
+
+    /// Reports the closes of both sides of the connection to the lifecycle log of a listener (the stream bound to
+    /// `client_addr` is the client)
+    pub(crate) fn track(&self, tracked: Tracked, client_addr: SocketAddr) {
+        *lock(&self.tracked) = Some((tracked, client_addr));
+    }
+
+    /// Returns the ID of the connection in the lifecycle log of the listener it went through (if any)
+    pub(crate) fn connection_id(&self) -> Option<u64> {
+        lock(&self.tracked).as_ref().map(|(tracked, _)| tracked.id)
+    }
+
+    fn close(&self, from: SocketAddr) {
+        let tracked = lock(&self.tracked).clone();
+
+        if let Some((tracked, client_addr)) = tracked {
+            let side = if from == client_addr {
+                Side::Client
+            } else {
+                Side::Server
+            };
+
+            tracked.lifecycle.close(tracked.id, side);
+        }
+    }
+
+    /// Records the event returned by given closure (which receives the events recorded so far)
+    fn record(&self, event: impl FnOnce(&[RecordedEvent]) -> Option<RecordedEvent>) {
+        self.update(|events| {
+            if let Some(event) = event(events) {
+                events.push(event);
+            }
+        });
+    }
+
+    /// Updates the events recorded so far with given closure
+    fn update(&self, f: impl FnOnce(&mut Vec<RecordedEvent>)) {
+        let mut slot = lock(&self.slot);
+
+        // Stop recording once the recording has been dropped by the user
+        if matches!(&*slot, Some(events) if Arc::strong_count(events) == 1) {
+            *slot = None;
+        }
+
+        if let Some(events) = &*slot {
+            f(&mut lock(events));
+        }
+    }
+}
+
+/// Records the events of one direction of a connection
+#[derive(Debug)]
+pub(crate) struct Tap {
+    recorder: Recorder,
+    from: SocketAddr,
+    to: SocketAddr,
+}
+
+impl Tap {
+    pub(crate) fn data(&self, bytes: &[u8]) {
+        self.recorder.record(|_| {
+            Some(RecordedEvent::Data {
+                from: self.from,
+                to: self.to,
+                bytes: bytes.to_vec(),
+                at: SystemTime::now(),
+            })
+        });
+    }
+
+    /// Removes the last data recorded by this tap (for data which could not be sent after all)
+    pub(crate) fn retract(&self) {
+        self.recorder.update(|events| {
+            let last = events.iter().rposition(
+                |event| matches!(event, RecordedEvent::Data { from, .. } if *from == self.from),
+            );
+
+            if let Some(index) = last {
+                events.remove(index);
+            }
+        });
+    }
+
+    pub(crate) fn shutdown(&self) {
+        self.recorder.record(|_| {
+            Some(RecordedEvent::Shutdown {
+                from: self.from,
+                to: self.to,
+                at: SystemTime::now(),
+            })
+        });
+        self.recorder.close(self.from);
+    }
+
+    /// Records a reset of the connection (`by_writer` is `true` if the stream writing to this direction reset it)
+    pub(crate) fn reset(&self, by_writer: bool) {
+        let (from, to) = match by_writer {
+            true => (self.from, self.to),
+            false => (self.to, self.from),
+        };
+
+        // Both directions are reset at once, so only the first reset is recorded
+        self.recorder.record(|events| match events.last() {
+            Some(RecordedEvent::Reset { .. }) => None,
+            _ => Some(RecordedEvent::Reset {
+                from,
+                to,
+                at: SystemTime::now(),
+            }),
+        });
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,303 @@
+//! Scripted network conditions (chunking and faults) for a pair of mock IO streams
+use std::{collections::VecDeque, ops::Range, sync::PoisonError};
+
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+use crate::link::Link;
+use crate::{
+    controller::{Controller, Direction},
+    error::Inner,
+    primitives::Mutex,
+};
+
+/// Policy for splitting the data written to a mock IO stream into separately delivered chunks
+///
+/// Data is split at given offsets of the byte stream (in addition to the boundaries of writes), so that the peer
+/// observes partial reads at those offsets.
+///
+/// ```rust
+/// # use std::io::{Read, Write};
+/// use mock_io::sync::{ChunkPolicy, MockStream};
+///
+/// let (mut client, mut server) = MockStream::pair_with_script(ChunkPolicy::new().split_at(2)).unwrap();
+/// client.write_all(b"hello").unwrap();
+///
+/// let mut buf = [0; 5];
+/// assert_eq!(2, server.read(&mut buf).unwrap());
+/// assert_eq!(3, server.read(&mut buf).unwrap());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkPolicy {
+    splits: Vec<u64>,
+}
+
+impl ChunkPolicy {
+    /// Creates a new chunk policy which does not split any writes
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Splits the byte stream at given offset (splits at offset zero have no effect)
+    pub fn split_at(mut self, offset: u64) -> Self {
+        if let Err(index) = self.splits.binary_search(&offset) {
+            self.splits.insert(index, offset);
+        }
+
+        self
+    }
+
+    /// Returns the offsets at which the byte stream is split
+    pub fn splits(&self) -> &[u64] {
+        &self.splits
+    }
+}
+
+/// Fault injected on the connection between a pair of mock IO streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// Resets the connection
+    Reset,
+    /// Silently drops all the data written afterwards by the stream
+    Blackhole,
+}
+
+/// Plan of faults injected when the data written to a mock IO stream reaches given offsets
+///
+/// ```rust
+/// # use std::io::{ErrorKind, Read, Write};
+/// use mock_io::sync::{Fault, FaultPlan, MockStream};
+///
+/// let plan = FaultPlan::new().inject(3, Fault::Reset);
+/// let (mut client, mut server) = MockStream::pair_with_script(plan).unwrap();
+/// assert_eq!(ErrorKind::ConnectionReset, client.write_all(b"hello").unwrap_err().kind());
+///
+/// let mut buf = [0; 5];
+/// assert_eq!(ErrorKind::ConnectionReset, server.read(&mut buf).unwrap_err().kind());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FaultPlan {
+    faults: Vec<(u64, Fault)>,
+}
+
+impl FaultPlan {
+    /// Creates a new fault plan without any faults
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Injects given fault once the stream has written `offset` bytes
+    pub fn inject(mut self, offset: u64, fault: Fault) -> Self {
+        let index = self.faults.partition_point(|(other, _)| *other <= offset);
+        self.faults.insert(index, (offset, fault));
+        self
+    }
+
+    /// Returns the faults along with the offsets at which they are injected
+    pub fn faults(&self) -> &[(u64, Fault)] {
+        &self.faults
+    }
+}
+
+/// Scripted network conditions of a connection between a pair of mock IO streams
+///
+/// Chunk policies, fault plans and links can be converted into a script which applies them to the data written by
+/// the first stream.
+///
+/// ```rust
+/// use mock_io::sync::{ChunkPolicy, Fault, FaultPlan, MockStream, Script};
+///
+/// let script = Script::new()
+///     .chunking(ChunkPolicy::new().split_at(1), ChunkPolicy::new())
+///     .faults(FaultPlan::new(), FaultPlan::new().inject(10, Fault::Reset));
+///
+/// let (client, server) = MockStream::pair_with_script(script).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub(crate) chunking: (ChunkPolicy, ChunkPolicy),
+    pub(crate) faults: (FaultPlan, FaultPlan),
+    #[cfg(any(feature = "async-tokio", feature = "sync"))]
+    pub(crate) links: (Link, Link),
+}
+
+impl Script {
+    /// Creates a new script which does not change the behavior of the streams
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the chunk policies of the data written by first stream (`policy_1`) and second stream (`policy_2`)
+    pub fn chunking(mut self, policy_1: ChunkPolicy, policy_2: ChunkPolicy) -> Self {
+        self.chunking = (policy_1, policy_2);
+        self
+    }
+
+    /// Sets the fault plans of the data written by first stream (`plan_1`) and second stream (`plan_2`)
+    pub fn faults(mut self, plan_1: FaultPlan, plan_2: FaultPlan) -> Self {
+        self.faults = (plan_1, plan_2);
+        self
+    }
+
+    /// Sets the links carrying the data written by first stream (`link_1`) and second stream (`link_2`)
+    #[cfg(any(feature = "async-tokio", feature = "sync"))]
+    pub fn links(mut self, link_1: Link, link_2: Link) -> Self {
+        self.links = (link_1, link_2);
+        self
+    }
+
+    /// Returns `true` if any of the links does not deliver data instantly
+    #[cfg(feature = "async-futures")]
+    pub(crate) fn has_links(&self) -> bool {
+        #[cfg(any(feature = "async-tokio", feature = "sync"))]
+        return !(self.links.0.is_instant() && self.links.1.is_instant());
+        #[cfg(not(any(feature = "async-tokio", feature = "sync")))]
+        return false;
+    }
+
+    /// Returns the shaping of the data written by first and second stream
+    pub(crate) fn shaping(
+        self,
+        controller_1: &Controller,
+        controller_2: &Controller,
+    ) -> (Shaping, Shaping) {
+        let (policy_1, policy_2) = self.chunking;
+        let (plan_1, plan_2) = self.faults;
+
+        (
+            Shaping::new(policy_1, plan_1, controller_1.clone()),
+            Shaping::new(policy_2, plan_2, controller_2.clone()),
+        )
+    }
+}
+
+impl From<ChunkPolicy> for Script {
+    fn from(policy: ChunkPolicy) -> Self {
+        Self::new().chunking(policy, ChunkPolicy::new())
+    }
+}
+
+impl From<FaultPlan> for Script {
+    fn from(plan: FaultPlan) -> Self {
+        Self::new().faults(plan, FaultPlan::new())
+    }
+}
+
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+impl From<Link> for Script {
+    fn from(link: Link) -> Self {
+        Self::new().links(link, Link::new())
+    }
+}
+
+/// Chunk policy and fault plan applied to the data written by a mock IO stream
+#[derive(Debug)]
+pub(crate) struct Shaping {
+    shaper: Mutex<Shaper>,
+    controller: Controller,
+}
+
+impl Shaping {
+    fn new(policy: ChunkPolicy, plan: FaultPlan, controller: Controller) -> Self {
+        Self {
+            shaper: Mutex::new(Shaper::new(policy, plan)),
+            controller,
+        }
+    }
+
+    /// Transmits written bytes chunk by chunk using given function and injects the faults which are due
+    pub(crate) fn apply(
+        &self,
+        bytes: &[u8],
+        mut transmit: impl FnMut(&[u8]) -> Result<(), Inner>,
+    ) -> Result<(), Inner> {
+        let steps = self
+            .shaper
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .shape(bytes.len());
+
+        for step in steps {
+            match step {
+                Step::Data(range) => transmit(&bytes[range])?,
+                Step::Fault(Fault::Reset) => {
+                    self.controller.reset();
+                    return Err(Inner::ConnectionReset);
+                }
+                Step::Fault(Fault::Blackhole) => self.controller.blackhole(Direction::Outgoing),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Step taken to transmit data written by a mock IO stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// Delivers given range of the written bytes as one chunk
+    Data(Range<usize>),
+    /// Injects a fault
+    Fault(Fault),
+}
+
+/// Splits the data written by a mock IO stream into chunks and faults
+#[derive(Debug, Default)]
+struct Shaper {
+    splits: VecDeque<u64>,
+    faults: VecDeque<(u64, Fault)>,
+    written: u64,
+}
+
+impl Shaper {
+    fn new(policy: ChunkPolicy, plan: FaultPlan) -> Self {
+        Self {
+            splits: policy.splits.into(),
+            faults: plan.faults.into(),
+            written: 0,
+        }
+    }
+
+    /// Returns the steps to transmit `len` written bytes. A fault is injected as soon as the number of delivered bytes
+    /// reaches its offset.
+    fn shape(&mut self, len: usize) -> Vec<Step> {
+        let start = self.written;
+        let end = start + len as u64;
+        let mut position = start;
+        let mut steps = Vec::new();
+
+        loop {
+            while let Some((_, fault)) = self
+                .faults
+                .front()
+                .filter(|(offset, _)| *offset <= position)
+            {
+                steps.push(Step::Fault(*fault));
+                self.faults.pop_front();
+            }
+
+            if position == end {
+                break;
+            }
+
+            while self.splits.front().is_some_and(|split| *split <= position) {
+                self.splits.pop_front();
+            }
+
+            let next = self
+                .splits
+                .front()
+                .copied()
+                .into_iter()
+                .chain(self.faults.front().map(|(offset, _)| *offset))
+                .fold(end, u64::min);
+
+            steps.push(Step::Data(
+                (position - start) as usize..(next - start) as usize,
+            ));
+            position = next;
+        }
+
+        self.written = end;
+        steps
+    }
+}
@@ -0,0 +1,159 @@
+//! Codecs and rules used by mock servers for answering decoded requests
+use std::{
+    fmt::Debug,
+    io,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use crate::error::{Error, Inner};
+
+/// Codec used by a mock server for decoding requests from and encoding responses to the bytes of a connection
+pub trait Codec: Clone + Send + 'static {
+    /// Type of requests decoded by the codec
+    type Request: Clone + Debug + PartialEq + Send + 'static;
+    /// Type of responses encoded by the codec
+    type Response: Clone + Debug + Send + 'static;
+
+    /// Decodes a request from the front of the buffer (removing its bytes). Returns `None` if the buffer does not
+    /// contain a complete request yet.
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<Self::Request>>;
+
+    /// Encodes a response at the end of the buffer
+    fn encode(&mut self, response: Self::Response, buf: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// Codec for newline delimited UTF-8 requests and responses (a trailing `\r` is stripped from requests)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineCodec;
+
+impl Codec for LineCodec {
+    type Request = String;
+    type Response = String;
+
+    fn decode(&mut self, buf: &mut Vec<u8>) -> io::Result<Option<String>> {
+        let end = match buf.iter().position(|byte| *byte == b'\n') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let mut line: Vec<u8> = buf.drain(..=end).collect();
+        line.pop();
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    fn encode(&mut self, response: String, buf: &mut Vec<u8>) -> io::Result<()> {
+        buf.extend_from_slice(response.as_bytes());
+        buf.push(b'\n');
+        Ok(())
+    }
+}
+
+/// Reply of a mock server to a decoded request
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Reply<R> {
+    /// Sends given response
+    Respond(R),
+    /// Sends given response after waiting for given duration
+    Delay(Duration, R),
+    /// Closes the connection
+    Close,
+    /// Resets the connection
+    Reset,
+}
+
+/// Rules of a mock server along with all the requests it has received (shared by all the connections)
+#[derive(Debug)]
+pub(crate) struct Rules<C: Codec> {
+    inner: Arc<Mutex<RulesState<C>>>,
+}
+
+#[derive(Debug)]
+struct RulesState<C: Codec> {
+    rules: Vec<Rule<C>>,
+    requests: Vec<C::Request>,
+}
+
+#[derive(Debug)]
+struct Rule<C: Codec> {
+    request: C::Request,
+    reply: Reply<C::Response>,
+    hits: usize,
+}
+
+impl<C: Codec> Rules<C> {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(RulesState {
+                rules: Default::default(),
+                requests: Default::default(),
+            })),
+        }
+    }
+
+    pub(crate) fn push(&self, request: C::Request, reply: Reply<C::Response>) {
+        self.lock().rules.push(Rule {
+            request,
+            reply,
+            hits: 0,
+        });
+    }
+
+    /// Records a request and returns the reply of the first rule matching it (if any)
+    pub(crate) fn reply(&self, request: C::Request) -> Option<Reply<C::Response>> {
+        let mut state = self.lock();
+
+        let reply = state
+            .rules
+            .iter_mut()
+            .find(|rule| rule.request == request)
+            .map(|rule| {
+                rule.hits += 1;
+                rule.reply.clone()
+            });
+
+        state.requests.push(request);
+        reply
+    }
+
+    pub(crate) fn requests(&self) -> Vec<C::Request> {
+        self.lock().requests.clone()
+    }
+
+    /// Returns an error listing the requests of the rules which were never matched
+    pub(crate) fn verify(&self) -> Result<(), Error> {
+        let missing: Vec<_> = self
+            .lock()
+            .rules
+            .iter()
+            .filter(|rule| rule.hits == 0)
+            .map(|rule| format!("{:?}", rule.request))
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Inner::MissingRequests(missing.join(", ")).into())
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RulesState<C>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C: Codec> Clone for Rules<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Descriptions of sessions played by mock peers
+use crate::script::Fault;
+
+/// Step of a session played by a mock peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionStep {
+    /// Peer sends given bytes (read by the stream under test as one chunk)
+    Send(Vec<u8>),
+    /// Peer expects the stream under test to write given bytes
+    Expect(Vec<u8>),
+    /// Peer injects a fault after the stream under test reads the bytes sent before
+    Fault(Fault),
+    /// Peer closes its side of the connection, i.e., the stream under test reads EOF after the bytes sent before
+    Close,
+}
+
+/// Description of a session played by a mock peer against the stream under test
+///
+/// A session is played deterministically: all the steps are applied before the stream under test is returned, so
+/// the same session always produces the same reads. Steps after a reset or close have no effect, and the peer closes
+/// its side of the connection after the last step, so that reads never block.
+///
+/// ```rust
+/// # use std::io::{Read, Write};
+/// use mock_io::sync::{MockStream, Session, SessionStep};
+///
+/// let session = Session::new()
+///     .step(SessionStep::Send(b"ping".to_vec()))
+///     .step(SessionStep::Expect(b"pong".to_vec()));
+///
+/// let (mut stream, mut peer) = MockStream::from_session(session);
+///
+/// let mut buf = Vec::new();
+/// stream.read_to_end(&mut buf).unwrap();
+/// assert_eq!(b"ping", &buf[..]);
+///
+/// stream.write_all(b"pong").unwrap();
+/// assert!(peer.check());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    steps: Vec<SessionStep>,
+}
+
+impl Session {
+    /// Creates a new empty session (the stream under test reads EOF right away)
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a step to the session
+    pub fn step(mut self, step: SessionStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Returns the steps of the session
+    pub fn steps(&self) -> &[SessionStep] {
+        &self.steps
+    }
+
+    /// Creates a session from fuzz input. The same input always produces the same session.
+    #[cfg(feature = "arbitrary")]
+    pub fn from_fuzz(data: &[u8]) -> Self {
+        arbitrary::Arbitrary::arbitrary_take_rest(arbitrary::Unstructured::new(data))
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn into_steps(self) -> Vec<SessionStep> {
+        self.steps
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+mod fuzz {
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    use super::{Session, SessionStep};
+    use crate::script::Fault;
+
+    impl<'a> Arbitrary<'a> for Fault {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(if u.arbitrary()? {
+                Fault::Reset
+            } else {
+                Fault::Blackhole
+            })
+        }
+
+        fn size_hint(_: usize) -> (usize, Option<usize>) {
+            (1, Some(1))
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for SessionStep {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(match u.int_in_range(0..=7u8)? {
+                0..=4 => SessionStep::Send(u.arbitrary()?),
+                5 => SessionStep::Expect(u.arbitrary()?),
+                6 => SessionStep::Fault(u.arbitrary()?),
+                _ => SessionStep::Close,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Session {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Session {
+                steps: u.arbitrary()?,
+            })
+        }
+
+        fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+            Ok(Session {
+                steps: Arbitrary::arbitrary_take_rest(u)?,
+            })
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Seeded scheduler for deterministic delivery of data across mock IO streams and listeners
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{Arc, PoisonError},
+};
+
+use crate::{
+    primitives::{Mutex, MutexGuard},
+    rng::Rng,
+};
+
+type Event = Box<dyn FnOnce() + Send>;
+
+/// Seeded scheduler which decides the order in which pending deliveries are performed across all the mock IO streams
+/// and listeners attached to it
+///
+/// Data written to a simulated stream (and connections made to a simulated listener) is not delivered right away.
+/// Instead, it is queued in the simulation and delivered when the simulation is stepped. On every step, the
+/// simulation uses its seed to pick the connection direction (or listener) whose next pending delivery is performed.
+/// Data in one direction of a connection is always delivered in the order it was written. As long as the deliveries
+/// are queued in the same order, running a simulation with the same seed replays the same interleaving.
+///
+/// ```rust
+/// # use std::io::{ErrorKind, Read, Write};
+/// use mock_io::sync::{MockStream, Simulation};
+///
+/// let simulation = Simulation::new(42);
+/// let (mut client, mut server) = MockStream::pair_with_simulation(&simulation);
+/// server.set_nonblocking(true).unwrap();
+///
+/// client.write_all(b"hello").unwrap();
+///
+/// let mut buf = [0; 5];
+/// assert_eq!(ErrorKind::WouldBlock, server.read(&mut buf).unwrap_err().kind());
+///
+/// assert_eq!(1, simulation.run_until_idle());
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"hello", &buf);
+/// ```
+#[derive(Clone)]
+pub struct Simulation {
+    inner: Arc<Mutex<Scheduler>>,
+}
+
+struct Scheduler {
+    seed: u64,
+    rng: Rng,
+    next_source: u64,
+    queues: BTreeMap<u64, VecDeque<Event>>,
+}
+
+impl Simulation {
+    /// Creates a new simulation with given seed
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Scheduler {
+                seed,
+                rng: Rng::new(seed),
+                next_source: 0,
+                queues: Default::default(),
+            })),
+        }
+    }
+
+    /// Returns the seed of the simulation
+    pub fn seed(&self) -> u64 {
+        self.lock().seed
+    }
+
+    /// Returns the number of pending deliveries
+    pub fn pending(&self) -> usize {
+        self.lock().queues.values().map(VecDeque::len).sum()
+    }
+
+    /// Performs the next pending delivery picked by the scheduler. Returns `false` if there are no pending
+    /// deliveries.
+    pub fn step(&self) -> bool {
+        let event = {
+            let mut scheduler = self.lock();
+            let sources = scheduler.queues.len() as u64;
+
+            if sources == 0 {
+                return false;
+            }
+
+            let index = (scheduler.rng.next_u64() % sources) as usize;
+            let source = scheduler.queues.keys().nth(index).copied();
+
+            source.and_then(|source| {
+                let queue = scheduler.queues.get_mut(&source)?;
+                let event = queue.pop_front();
+
+                if queue.is_empty() {
+                    scheduler.queues.remove(&source);
+                }
+
+                event
+            })
+        };
+
+        match event {
+            Some(event) => {
+                event();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Performs pending deliveries until there are none left (including the ones queued while running) and returns
+    /// the number of deliveries performed
+    pub fn run_until_idle(&self) -> usize {
+        let mut steps = 0;
+
+        while self.step() {
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// Registers a new source of deliveries (i.e., one direction of a connection or a listener)
+    pub(crate) fn source(&self) -> u64 {
+        let mut scheduler = self.lock();
+        let source = scheduler.next_source;
+        scheduler.next_source += 1;
+        source
+    }
+
+    /// Queues a delivery from given source
+    pub(crate) fn schedule(&self, source: u64, event: impl FnOnce() + Send + 'static) {
+        self.lock()
+            .queues
+            .entry(source)
+            .or_default()
+            .push_back(Box::new(event));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Scheduler> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Simulation")
+            .field("seed", &self.seed())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
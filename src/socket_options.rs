@@ -0,0 +1,121 @@
+//! Emulated socket options recorded by mock IO streams
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, PoisonError,
+    },
+    time::Duration,
+};
+
+use crate::primitives::{Mutex, MutexGuard};
+
+/// First port assigned to mock streams created without explicit addresses (and mock datagram sockets bound to port `0`)
+pub(crate) const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// Next port assigned to a mock stream created without explicit address
+static NEXT_PORT: AtomicU16 = AtomicU16::new(EPHEMERAL_PORT_START);
+
+/// Snapshot of socket options configured on a mock IO stream
+///
+/// Mock streams only record these values (they do not change the behavior of the stream), so that tests can assert
+/// that the code under test configured the socket correctly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SocketOptions {
+    /// Value of `TCP_NODELAY` option
+    pub nodelay: bool,
+    /// Value of `SO_LINGER` option
+    pub linger: Option<Duration>,
+    /// Value of `IP_TTL` option
+    pub ttl: u32,
+    /// Keepalive idle time (`None` if `SO_KEEPALIVE` is disabled)
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: false,
+            linger: None,
+            ttl: 64,
+            keepalive: None,
+        }
+    }
+}
+
+/// Returns a pair of loopback addresses with distinct ephemeral ports for mock streams created without explicit
+/// addresses
+pub(crate) fn default_addrs() -> (SocketAddr, SocketAddr) {
+    (next_addr(), next_addr())
+}
+
+fn next_addr() -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), next_port())
+}
+
+/// Returns the next ephemeral port (shared by mock streams and mock datagram sockets, so that their ports do not
+/// collide)
+pub(crate) fn next_port() -> u16 {
+    NEXT_PORT
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |port| {
+            Some(port.checked_add(1).unwrap_or(EPHEMERAL_PORT_START))
+        })
+        .unwrap_or(EPHEMERAL_PORT_START)
+}
+
+/// Socket options and address of a mock IO stream along with the options and address of its peer
+#[derive(Debug)]
+pub(crate) struct SharedOptions {
+    local: Arc<Mutex<SocketOptions>>,
+    peer: Arc<Mutex<SocketOptions>>,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+}
+
+impl SharedOptions {
+    /// Creates socket options for a pair of connected mock streams with given addresses
+    pub(crate) fn pair(addr_1: SocketAddr, addr_2: SocketAddr) -> (Self, Self) {
+        let options_1 = Arc::new(Mutex::new(SocketOptions::default()));
+        let options_2 = Arc::new(Mutex::new(SocketOptions::default()));
+
+        (
+            Self {
+                local: options_1.clone(),
+                peer: options_2.clone(),
+                local_addr: addr_1,
+                peer_addr: addr_2,
+            },
+            Self {
+                local: options_2,
+                peer: options_1,
+                local_addr: addr_2,
+                peer_addr: addr_1,
+            },
+        )
+    }
+
+    pub(crate) fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub(crate) fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    pub(crate) fn get(&self) -> SocketOptions {
+        lock(&self.local).clone()
+    }
+
+    pub(crate) fn peer(&self) -> SocketOptions {
+        lock(&self.peer).clone()
+    }
+
+    pub(crate) fn update(&self, f: impl FnOnce(&mut SocketOptions)) {
+        f(&mut lock(&self.local))
+    }
+}
+
+fn lock(options: &Mutex<SocketOptions>) -> MutexGuard<'_, SocketOptions> {
+    options.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,97 @@
+//! Runtime independent helpers for spawning mock peers of the futures backend
+//!
+//! Helpers take a spawner (any `Fn(Task)` closure), so they work with any executor (e.g., `async-std`, `smol` or
+//! `futures::executor`).
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::futures::{spawn_peer, MockListener, MockStream, Peer};
+
+/// Future spawned by the helpers and mock servers of the futures backend
+pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Spawner stored by the types of the futures backend which spawn tasks later on (e.g., `MockServer`)
+pub(crate) type Spawner = Arc<dyn Fn(Task) + Send + Sync>;
+
+/// Accepts connections from given listener on a spawned task (until all the handles of the listener are dropped) and
+/// spawns a task running `handler` for each of them
+pub fn spawn_accept_loop<S, H, F>(spawner: S, listener: MockListener, mut handler: H)
+where
+    S: Fn(Task) + Clone + Send + Sync + 'static,
+    H: FnMut(MockStream) -> F + Send + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let spawner_clone = spawner.clone();
+
+    spawner(Box::pin(async move {
+        while let Ok(stream) = listener.accept().await {
+            spawner_clone(Box::pin(handler(stream)));
+        }
+    }));
+}
+
+/// Spawns a task which writes all the data read from given stream back to it (until EOF or an error)
+///
+/// ```rust
+/// use futures_util::{AsyncReadExt, AsyncWriteExt};
+/// use mock_io::futures::{spawn_echo, MockStream};
+///
+/// async_std::task::block_on(async {
+///     let spawner = |task| {
+///         async_std::task::spawn(task);
+///     };
+///
+///     let (mut client, server) = MockStream::pair();
+///     spawn_echo(&spawner, server);
+///
+///     client.write_all(b"hello").await.unwrap();
+///
+///     let mut buf = [0; 5];
+///     client.read_exact(&mut buf).await.unwrap();
+///     assert_eq!(b"hello", &buf);
+/// });
+/// ```
+pub fn spawn_echo<S>(spawner: &S, stream: MockStream)
+where
+    S: Fn(Task),
+{
+    spawn_peer(spawner, stream, Peer::Echo);
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+
+    use futures_executor::ThreadPool;
+    use futures_util::{AsyncReadExt, AsyncWriteExt};
+
+    async fn check_echo_server(spawner: impl Fn(Task) + Clone + Send + Sync + 'static) {
+        let (listener, handle) = MockListener::new();
+
+        let echo_spawner = spawner.clone();
+        spawn_accept_loop(spawner, listener, move |stream| {
+            spawn_echo(&echo_spawner, stream);
+            async {}
+        });
+
+        let mut client = MockStream::connect(&handle).await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(b"hello", &buf);
+        client.close().await.unwrap();
+        assert_eq!(0, client.read(&mut buf).await.unwrap());
+    }
+
+    #[test]
+    fn check_runtimes() {
+        async_std::task::block_on(check_echo_server(|task| {
+            async_std::task::spawn(task);
+        }));
+
+        smol::block_on(check_echo_server(|task| smol::spawn(task).detach()));
+
+        let pool = ThreadPool::new().unwrap();
+        futures_executor::block_on(check_echo_server(move |task| pool.spawn_ok(task)));
+    }
+}
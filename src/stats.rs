@@ -0,0 +1,209 @@
+//! Statistics collected by mock IO streams
+use std::{
+    sync::{Arc, PoisonError},
+    time::Instant,
+};
+
+use crate::primitives::{
+    atomic::{AtomicU64, Ordering},
+    Mutex, MutexGuard,
+};
+
+/// Snapshot of statistics of a mock IO stream (or aggregate of all the streams accepted by a mock IO listener)
+///
+/// Snapshots taken at different phases of a test can be diffed using `since`.
+///
+/// ```rust
+/// # use std::io::Write;
+/// use mock_io::sync::MockStream;
+///
+/// let (mut client, _server) = MockStream::pair();
+///
+/// client.write_all(b"hello").unwrap();
+/// let before = client.stats();
+///
+/// client.write_all(b"world").unwrap();
+/// client.flush().unwrap();
+///
+/// let stats = client.stats().since(&before);
+/// assert_eq!(5, stats.bytes_written);
+/// assert_eq!(1, stats.flush_calls);
+/// assert_eq!(10, client.stats().max_pending);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Number of bytes written
+    pub bytes_written: u64,
+    /// Number of bytes read
+    pub bytes_read: u64,
+    /// Number of messages (non-empty writes) sent to the peer
+    pub messages_written: u64,
+    /// Number of messages received from the peer
+    pub messages_read: u64,
+    /// Number of calls to `write` (and its equivalents)
+    pub write_calls: u64,
+    /// Number of completed calls to `read` (and its equivalents)
+    pub read_calls: u64,
+    /// Number of calls to `flush`
+    pub flush_calls: u64,
+    /// Maximum number of bytes written by the stream which were waiting to be read by the peer at the same time
+    /// (bytes dropped on the way, e.g., by a blackhole, are never waiting)
+    pub max_pending: u64,
+    /// Time of the last read, write or flush
+    pub last_activity: Option<Instant>,
+}
+
+impl Stats {
+    /// Returns the statistics collected since `earlier` snapshot was taken
+    ///
+    /// Counters are subtracted while `max_pending` and `last_activity` are taken from this snapshot.
+    pub fn since(&self, earlier: &Stats) -> Stats {
+        Stats {
+            bytes_written: self.bytes_written.saturating_sub(earlier.bytes_written),
+            bytes_read: self.bytes_read.saturating_sub(earlier.bytes_read),
+            messages_written: self
+                .messages_written
+                .saturating_sub(earlier.messages_written),
+            messages_read: self.messages_read.saturating_sub(earlier.messages_read),
+            write_calls: self.write_calls.saturating_sub(earlier.write_calls),
+            read_calls: self.read_calls.saturating_sub(earlier.read_calls),
+            flush_calls: self.flush_calls.saturating_sub(earlier.flush_calls),
+            max_pending: self.max_pending,
+            last_activity: self.last_activity,
+        }
+    }
+}
+
+/// Aggregate statistics of all the streams accepted by a mock IO listener
+pub(crate) type Aggregate = Arc<Mutex<Stats>>;
+
+/// Statistics of a mock IO stream shared by its halves
+#[derive(Debug, Clone)]
+pub(crate) struct StatsCell {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Debug)]
+struct StatsInner {
+    stats: Mutex<Stats>,
+    aggregate: Mutex<Option<Aggregate>>,
+    /// Bytes written by this stream which are not yet read by the peer
+    outgoing: Arc<AtomicU64>,
+    /// Bytes written by the peer which are not yet read by this stream
+    incoming: Arc<AtomicU64>,
+}
+
+impl StatsCell {
+    /// Creates statistics for a pair of connected mock streams
+    pub(crate) fn pair() -> (Self, Self) {
+        let pending_1 = Arc::new(AtomicU64::new(0));
+        let pending_2 = Arc::new(AtomicU64::new(0));
+
+        (
+            Self::new(pending_1.clone(), pending_2.clone()),
+            Self::new(pending_2, pending_1),
+        )
+    }
+
+    fn new(outgoing: Arc<AtomicU64>, incoming: Arc<AtomicU64>) -> Self {
+        Self {
+            inner: Arc::new(StatsInner {
+                stats: Default::default(),
+                aggregate: Default::default(),
+                outgoing,
+                incoming,
+            }),
+        }
+    }
+
+    /// Adds the statistics of this stream to given listener aggregate from now on
+    pub(crate) fn attach(&self, aggregate: Aggregate) {
+        *lock(&self.inner.aggregate) = Some(aggregate);
+    }
+
+    pub(crate) fn get(&self) -> Stats {
+        lock(&self.inner.stats).clone()
+    }
+
+    /// Returns the number of bytes written by this stream which are not yet read by the peer
+    pub(crate) fn pending(&self) -> u64 {
+        self.inner.outgoing.load(Ordering::SeqCst)
+    }
+
+    /// Records a write of `len` bytes (`sent` is `false` if nothing was sent to the peer)
+    pub(crate) fn write(&self, len: usize, sent: bool) {
+        self.update(|stats| {
+            stats.write_calls += 1;
+            stats.bytes_written += len as u64;
+            stats.messages_written += u64::from(sent);
+        });
+    }
+
+    /// Records `len` bytes which will be delivered to the peer (bytes dropped on the way are never recorded, so they
+    /// are not counted as pending)
+    pub(crate) fn enqueue(&self, len: usize) {
+        let len = len as u64;
+        let pending = self.inner.outgoing.fetch_add(len, Ordering::SeqCst) + len;
+
+        self.update(|stats| stats.max_pending = stats.max_pending.max(pending));
+    }
+
+    /// Records `len` bytes recorded by `enqueue` which were discarded before being delivered to the peer
+    pub(crate) fn discard(&self, len: usize) {
+        let _ = self
+            .inner
+            .outgoing
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                Some(pending.saturating_sub(len as u64))
+            });
+    }
+
+    /// Records a completed read of `len` bytes. `consumed` is the number of bytes removed from the incoming queue
+    /// (can be larger than `len` if a message was truncated).
+    pub(crate) fn read(&self, len: usize, consumed: usize) {
+        let consumed = consumed as u64;
+        let _ = self
+            .inner
+            .incoming
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                Some(pending.saturating_sub(consumed))
+            });
+
+        self.update(|stats| {
+            stats.read_calls += 1;
+            stats.bytes_read += len as u64;
+        });
+    }
+
+    /// Records a message received from the peer
+    pub(crate) fn message(&self) {
+        self.update(|stats| stats.messages_read += 1);
+    }
+
+    /// Records a flush (`sent` is `false` if nothing was sent to the peer)
+    pub(crate) fn flush(&self, sent: bool) {
+        self.update(|stats| {
+            stats.flush_calls += 1;
+            stats.messages_written += u64::from(sent);
+        });
+    }
+
+    fn update(&self, f: impl Fn(&mut Stats)) {
+        let now = Instant::now();
+
+        let mut stats = lock(&self.inner.stats);
+        f(&mut stats);
+        stats.last_activity = Some(now);
+
+        if let Some(aggregate) = &*lock(&self.inner.aggregate) {
+            let mut aggregate = lock(aggregate);
+            f(&mut aggregate);
+            aggregate.last_activity = Some(now);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
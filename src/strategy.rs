@@ -0,0 +1,215 @@
+//! `proptest` strategies for the network conditions of mock IO streams
+//!
+//! Every generated value can be converted into a `Script`, so it can be used to create a pair of mock streams directly
+//! (e.g., `MockStream::pair_with_script(policy)`). Generated values shrink towards fewer and earlier splits and
+//! faults, and towards links without any latency.
+//!
+//! ```rust
+//! # use std::io::{Read, Write};
+//! use mock_io::{strategy::chunk_policy, sync::MockStream};
+//! use proptest::{prop_assert_eq, test_runner::TestRunner};
+//!
+//! TestRunner::default()
+//!     .run(&chunk_policy(16), |policy| {
+//!         let (mut client, mut server) = MockStream::pair_with_script(policy).unwrap();
+//!         client.write_all(b"hello world").unwrap();
+//!         drop(client);
+//!
+//!         let mut buf = Vec::new();
+//!         server.read_to_end(&mut buf).unwrap();
+//!         prop_assert_eq!(b"hello world", &buf[..]);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+use std::time::Duration;
+
+use proptest::{
+    arbitrary::Arbitrary,
+    collection::vec,
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+use crate::link::Link;
+use crate::script::{ChunkPolicy, Fault, FaultPlan, Script};
+
+/// Maximum offset of splits and faults generated by `Arbitrary` implementations
+const DEFAULT_MAX_OFFSET: u64 = 1024;
+
+/// Returns a strategy for chunk policies splitting the first `max_offset` bytes of a stream at up to eight offsets
+pub fn chunk_policy(max_offset: u64) -> impl Strategy<Value = ChunkPolicy> {
+    vec(1..=max_offset.max(1), 0..=8).prop_map(|splits| {
+        splits
+            .into_iter()
+            .fold(ChunkPolicy::new(), ChunkPolicy::split_at)
+    })
+}
+
+/// Returns a strategy for faults
+pub fn fault() -> impl Strategy<Value = Fault> {
+    proptest::prop_oneof![Just(Fault::Reset), Just(Fault::Blackhole)]
+}
+
+/// Returns a strategy for fault plans injecting up to two faults within the first `max_offset` bytes of a stream
+pub fn fault_plan(max_offset: u64) -> impl Strategy<Value = FaultPlan> {
+    vec((0..=max_offset, fault()), 0..=2).prop_map(|faults| {
+        faults
+            .into_iter()
+            .fold(FaultPlan::new(), |plan, (offset, fault)| {
+                plan.inject(offset, fault)
+            })
+    })
+}
+
+/// Returns a strategy for links with up to 50ms of delay, up to 10ms of jitter and an optional rate limit
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+pub fn latency_profile() -> impl Strategy<Value = Link> {
+    (
+        0..=50u64,
+        0..=10u64,
+        proptest::option::of(1024..=1_048_576u64),
+        proptest::num::u64::ANY,
+    )
+        .prop_map(|(delay, jitter, rate, seed)| {
+            let link = Link::new()
+                .delay(Duration::from_millis(delay))
+                .jitter(Duration::from_millis(jitter))
+                .seed(seed);
+
+            match rate {
+                Some(rate) => link.rate(rate),
+                None => link,
+            }
+        })
+}
+
+/// Returns a strategy for scripts with chunk policies, fault plans and links in both directions (splits and faults
+/// are generated within the first `max_offset` bytes)
+///
+/// Scripts with links cannot be used with the `async-futures` backend (see `script` for scripts usable with every
+/// backend).
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+pub fn script_with_links(max_offset: u64) -> impl Strategy<Value = Script> {
+    (
+        (chunk_policy(max_offset), chunk_policy(max_offset)),
+        (fault_plan(max_offset), fault_plan(max_offset)),
+        (latency_profile(), latency_profile()),
+    )
+        .prop_map(
+            |((policy_1, policy_2), (plan_1, plan_2), (link_1, link_2))| {
+                Script::new()
+                    .chunking(policy_1, policy_2)
+                    .faults(plan_1, plan_2)
+                    .links(link_1, link_2)
+            },
+        )
+}
+
+/// Returns a strategy for scripts with chunk policies and fault plans in both directions (splits and faults are
+/// generated within the first `max_offset` bytes)
+pub fn script(max_offset: u64) -> impl Strategy<Value = Script> {
+    (
+        (chunk_policy(max_offset), chunk_policy(max_offset)),
+        (fault_plan(max_offset), fault_plan(max_offset)),
+    )
+        .prop_map(|((policy_1, policy_2), (plan_1, plan_2))| {
+            Script::new()
+                .chunking(policy_1, policy_2)
+                .faults(plan_1, plan_2)
+        })
+}
+
+impl Arbitrary for ChunkPolicy {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        chunk_policy(DEFAULT_MAX_OFFSET).boxed()
+    }
+}
+
+impl Arbitrary for Fault {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        fault().boxed()
+    }
+}
+
+impl Arbitrary for FaultPlan {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        fault_plan(DEFAULT_MAX_OFFSET).boxed()
+    }
+}
+
+#[cfg(any(feature = "async-tokio", feature = "sync"))]
+impl Arbitrary for Link {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        latency_profile().boxed()
+    }
+}
+
+impl Arbitrary for Script {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        script(DEFAULT_MAX_OFFSET).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::test_runner::TestRunner;
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn check_chunking_shrinks_to_smallest_split() {
+        use std::io::{Read, Write};
+
+        use proptest::test_runner::TestError;
+
+        use crate::sync::MockStream;
+
+        let mut runner = TestRunner::deterministic();
+
+        let result = runner.run(&chunk_policy(64), |policy| {
+            let (mut client, mut server) = MockStream::pair_with_script(policy).unwrap();
+            client.write_all(&[0; 64]).unwrap();
+
+            let mut buf = [0; 64];
+            let len = server.read(&mut buf).unwrap();
+            proptest::prop_assert_eq!(64, len);
+            Ok(())
+        });
+
+        match result {
+            Err(TestError::Fail(_, policy)) => assert_eq!(ChunkPolicy::new().split_at(1), policy),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[cfg(feature = "async-futures")]
+    #[test]
+    fn check_scripts_build_futures_streams() {
+        use crate::futures::MockStream;
+
+        TestRunner::default()
+            .run(&script(16), |script| {
+                proptest::prop_assert!(MockStream::pair_with_script(script).is_ok());
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -1,3 +1,32 @@
 //! Mock IO stream and listener in sync context
+pub use crate::controller::{Controller, Direction};
+pub use crate::datagram::DatagramFaults;
+pub use crate::flush::FlushMode;
+pub use crate::intercept::{Action, Interceptor};
+pub use crate::lifecycle::ConnectionEvent;
+pub use crate::link::Link;
 pub use crate::mock_listener::sync::*;
+pub use crate::mock_peer::sync::*;
+pub use crate::mock_proxy::sync::*;
+pub use crate::mock_seqpacket::sync::*;
+pub use crate::mock_server::sync::*;
+pub use crate::mock_session::sync::*;
 pub use crate::mock_stream::sync::*;
+#[cfg(feature = "rustls")]
+pub use crate::mock_tls::sync::*;
+pub use crate::mock_udp_socket::sync::*;
+pub use crate::mock_unix_listener::sync::*;
+pub use crate::mock_unix_stream::sync::*;
+pub use crate::peer::Peer;
+pub use crate::recording::{RecordedEvent, Recording};
+pub use crate::script::{ChunkPolicy, Fault, FaultPlan, Script};
+pub use crate::server::{Codec, LineCodec, Reply};
+pub use crate::session::{Session, SessionStep};
+pub use crate::simulation::Simulation;
+pub use crate::socket_options::SocketOptions;
+pub use crate::stats::Stats;
+pub use crate::tcp_bridge::sync::*;
+pub use crate::timeline::{Matcher, Side, Timeline};
+#[cfg(feature = "rustls")]
+pub use crate::tls::{TestCa, TlsFault, TlsOptions};
+pub use crate::unix::{Ancillary, Credentials, UnixSocketAddr};
@@ -0,0 +1,4 @@
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(all(feature = "async-tokio", not(loom)))]
+pub mod tokio;
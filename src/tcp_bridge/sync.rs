@@ -0,0 +1,169 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    thread,
+};
+
+use crate::{
+    error::Error,
+    sync::{Handle, MockStream, ReadHalf, WriteHalf},
+};
+
+/// Size of the buffer used for reading data flowing through the bridge
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Bridge between real TCP sockets on loopback interface and synchronous mock IO streams
+///
+/// Data is pumped in both directions on background threads. EOF on one side is propagated to the other side, i.e.,
+/// dropping a mock stream shuts down the write side of the TCP socket and vice versa.
+///
+/// ```rust
+/// # use std::{io::{Read, Write}, net::TcpStream};
+/// use mock_io::sync::{MockListener, TcpBridge};
+///
+/// let (listener, handle) = MockListener::new();
+/// let bridge = TcpBridge::bind(handle).unwrap();
+///
+/// let mut client = TcpStream::connect(bridge.local_addr().unwrap()).unwrap();
+/// bridge.accept().unwrap();
+/// let mut server = listener.accept().unwrap();
+///
+/// client.write_all(b"hello").unwrap();
+///
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(b"hello", &buf);
+/// ```
+pub struct TcpBridge {
+    listener: TcpListener,
+    handle: Handle,
+}
+
+impl TcpBridge {
+    /// Binds a TCP listener to a free port on loopback interface. Every accepted socket is connected to the mock IO
+    /// listener of given handle.
+    pub fn bind(handle: Handle) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        Ok(Self { listener, handle })
+    }
+
+    /// Returns the address of the TCP listener of this bridge
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(Into::into)
+    }
+
+    /// Accepts one TCP socket, connects it to the mock IO listener and starts pumping data between them
+    pub fn accept(&self) -> Result<(), Error> {
+        let (socket, _) = self.listener.accept()?;
+        let stream = MockStream::connect(&self.handle)?;
+
+        pump(stream, socket)
+    }
+
+    /// Accepts TCP sockets until an error occurs
+    pub fn run(self) -> Result<(), Error> {
+        loop {
+            self.accept()?;
+        }
+    }
+
+    /// Connects to a real TCP address and starts pumping data between given mock stream and the TCP socket (reverse
+    /// mode)
+    pub fn forward(stream: MockStream, addr: impl ToSocketAddrs) -> Result<(), Error> {
+        let socket = TcpStream::connect(addr)?;
+        pump(stream, socket)
+    }
+}
+
+fn pump(stream: MockStream, socket: TcpStream) -> Result<(), Error> {
+    let socket_clone = socket.try_clone()?;
+    let (read_half, write_half) = stream.split();
+
+    thread::spawn(move || mock_to_socket(read_half, socket_clone));
+    thread::spawn(move || socket_to_mock(socket, write_half));
+
+    Ok(())
+}
+
+fn mock_to_socket(mut read_half: ReadHalf, mut socket: TcpStream) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        match read_half.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => {
+                if socket.write_all(&buf[..len]).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = socket.shutdown(Shutdown::Write);
+}
+
+fn socket_to_mock(mut socket: TcpStream, mut write_half: WriteHalf) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        match socket.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => {
+                if write_half.write_all(&buf[..len]).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Dropping the write half signals EOF to the mock stream
+    drop(write_half);
+    let _ = socket.shutdown(Shutdown::Read);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sync::MockListener;
+
+    #[test]
+    fn check_eof_propagation() {
+        let (listener, handle) = MockListener::new();
+        let bridge = TcpBridge::bind(handle).unwrap();
+
+        let mut client = TcpStream::connect(bridge.local_addr().unwrap()).unwrap();
+        bridge.accept().unwrap();
+        let mut server = listener.accept().unwrap();
+
+        client.write_all(b"ping").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).unwrap();
+        assert_eq!(b"ping", request.as_slice());
+
+        server.write_all(b"pong").unwrap();
+        drop(server);
+
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(b"pong", response.as_slice());
+    }
+
+    #[test]
+    fn check_forward() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let (mut client, remote) = MockStream::pair();
+        TcpBridge::forward(remote, listener.local_addr().unwrap()).unwrap();
+
+        let (mut server, _) = listener.accept().unwrap();
+
+        client.write_all(b"hello").unwrap();
+        drop(client);
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).unwrap();
+        assert_eq!(b"hello", request.as_slice());
+    }
+}
@@ -0,0 +1,206 @@
+use std::net::SocketAddr;
+
+use tokio::{
+    io::ReadBuf,
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpStream, ToSocketAddrs,
+    },
+};
+
+use crate::{
+    error::Error,
+    tokio::{Handle, MockStream, ReadHalf, WriteHalf},
+};
+
+/// Size of the buffer used for reading data flowing through the bridge
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Bridge between real TCP sockets on loopback interface and asynchronous mock IO streams
+///
+/// Data is pumped in both directions on tasks spawned on the current tokio runtime. EOF on one side is propagated to
+/// the other side, i.e., shutting down (or dropping) a mock stream shuts down the write side of the TCP socket and
+/// vice versa.
+pub struct TcpBridge {
+    listener: TcpListener,
+    handle: Handle,
+}
+
+impl TcpBridge {
+    /// Binds a TCP listener to a free port on loopback interface. Every accepted socket is connected to the mock IO
+    /// listener of given handle.
+    pub async fn bind(handle: Handle) -> Result<Self, Error> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        Ok(Self { listener, handle })
+    }
+
+    /// Returns the address of the TCP listener of this bridge
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        self.listener.local_addr().map_err(Into::into)
+    }
+
+    /// Accepts one TCP socket, connects it to the mock IO listener and starts pumping data between them
+    pub async fn accept(&self) -> Result<(), Error> {
+        let (socket, _) = self.listener.accept().await?;
+        let stream = MockStream::connect(&self.handle)?;
+
+        pump(stream, socket);
+        Ok(())
+    }
+
+    /// Accepts TCP sockets until an error occurs
+    pub async fn run(self) -> Result<(), Error> {
+        loop {
+            self.accept().await?;
+        }
+    }
+
+    /// Connects to a real TCP address and starts pumping data between given mock stream and the TCP socket (reverse
+    /// mode)
+    pub async fn forward(stream: MockStream, addr: impl ToSocketAddrs) -> Result<(), Error> {
+        let socket = TcpStream::connect(addr).await?;
+
+        pump(stream, socket);
+        Ok(())
+    }
+}
+
+fn pump(stream: MockStream, socket: TcpStream) {
+    let (socket_read, socket_write) = socket.into_split();
+    let (read_half, write_half) = stream.split();
+
+    tokio::spawn(mock_to_socket(read_half, socket_write));
+    tokio::spawn(socket_to_mock(socket_read, write_half));
+}
+
+async fn mock_to_socket(mut read_half: ReadHalf, socket: OwnedWriteHalf) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        let mut read_buf = ReadBuf::new(&mut buf);
+
+        if read_half.receive(&mut read_buf).await.is_err() || read_buf.filled().is_empty() {
+            break;
+        }
+
+        let mut bytes = read_buf.filled();
+
+        while !bytes.is_empty() {
+            if socket.writable().await.is_err() {
+                return;
+            }
+
+            match socket.try_write(bytes) {
+                Ok(len) => bytes = &bytes[len..],
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(_) => return,
+            }
+        }
+    }
+
+    // Dropping the write half of the socket shuts down its write side
+    drop(socket);
+}
+
+async fn socket_to_mock(socket: OwnedReadHalf, write_half: WriteHalf) {
+    let mut buf = vec![0; BUFFER_SIZE];
+
+    loop {
+        if socket.readable().await.is_err() {
+            break;
+        }
+
+        match socket.try_read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => {
+                if write_half.send(&buf[..len]).is_err() {
+                    break;
+                }
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(_) => break,
+        }
+    }
+
+    // Dropping the write half signals EOF to the mock stream
+    drop(write_half);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::tokio::MockListener;
+
+    #[tokio::test]
+    async fn check_eof_propagation() {
+        let (mut listener, handle) = MockListener::new();
+        let bridge = TcpBridge::bind(handle).await.unwrap();
+
+        let mut client = TcpStream::connect(bridge.local_addr().unwrap())
+            .await
+            .unwrap();
+        bridge.accept().await.unwrap();
+        let mut server = listener.accept().await.unwrap();
+
+        client.write_all(b"ping").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(b"ping", request.as_slice());
+
+        server.write_all(b"pong").await.unwrap();
+        drop(server);
+
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        assert_eq!(b"pong", response.as_slice());
+    }
+
+    #[tokio::test]
+    async fn check_shutdown_propagation() {
+        let (mut listener, handle) = MockListener::new();
+        let bridge = TcpBridge::bind(handle).await.unwrap();
+
+        let mut client = TcpStream::connect(bridge.local_addr().unwrap())
+            .await
+            .unwrap();
+        bridge.accept().await.unwrap();
+        let mut server = listener.accept().await.unwrap();
+
+        server.write_all(b"hello").await.unwrap();
+        server.shutdown().await.unwrap();
+
+        let mut greeting = Vec::new();
+        client.read_to_end(&mut greeting).await.unwrap();
+        assert_eq!(b"hello", greeting.as_slice());
+
+        client.write_all(b"bye").await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut reply = Vec::new();
+        server.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(b"bye", reply.as_slice());
+    }
+
+    #[tokio::test]
+    async fn check_forward() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let (mut client, remote) = MockStream::pair();
+        TcpBridge::forward(remote, listener.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.write_all(b"hello").await.unwrap();
+        drop(client);
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(b"hello", request.as_slice());
+    }
+}
@@ -0,0 +1,415 @@
+//! Assertions on the order of the events recorded on a connection between a pair of mock IO streams
+use std::{
+    ascii,
+    fmt::{self, Display, Formatter},
+    net::SocketAddr,
+    time::SystemTime,
+};
+
+use crate::recording::RecordedEvent;
+
+/// Width of a direction column in the rendered timeline
+const COLUMN_WIDTH: usize = 32;
+
+/// Side of a recorded connection (the stream which started the recording is the client)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Stream which started the recording
+    Client,
+    /// Peer of the stream which started the recording
+    Server,
+}
+
+impl Side {
+    /// Matches the write which completes the first occurrence of given bytes in the data written by this side (the
+    /// bytes may be split across writes). An empty pattern matches the first write of this side.
+    pub fn wrote(self, bytes: impl AsRef<[u8]>) -> Matcher {
+        Matcher(MatcherKind::Data(self, bytes.as_ref().to_vec()))
+    }
+
+    /// Matches the shutdown of the write side of this side
+    pub fn shutdown(self) -> Matcher {
+        Matcher(MatcherKind::Shutdown(self))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Client => "client",
+            Self::Server => "server",
+        }
+    }
+}
+
+/// Matcher for an event in a timeline (created using [`Side::wrote`], [`Side::shutdown`] or [`Matcher::reset`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matcher(MatcherKind);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MatcherKind {
+    Data(Side, Vec<u8>),
+    Shutdown(Side),
+    Reset,
+}
+
+impl Matcher {
+    /// Matches the reset of the connection
+    pub fn reset() -> Self {
+        Self(MatcherKind::Reset)
+    }
+}
+
+impl Display for Matcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            MatcherKind::Data(side, bytes) => write!(f, "{} wrote {}", side.name(), escape(bytes)),
+            MatcherKind::Shutdown(side) => write!(f, "{} shutdown", side.name()),
+            MatcherKind::Reset => write!(f, "reset"),
+        }
+    }
+}
+
+/// Combined log of the events in both directions of a recorded connection with assertions on their order
+///
+/// Assertions panic with a message showing both directions of the connection side by side.
+///
+/// ```rust
+/// # use std::io::{Read, Write};
+/// use mock_io::sync::{MockStream, Side};
+///
+/// let (mut client, mut server) = MockStream::pair();
+/// let recording = client.record();
+///
+/// client.write_all(b"ping\n").unwrap();
+///
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).unwrap();
+/// server.write_all(b"pong\n").unwrap();
+/// drop(server);
+///
+/// recording
+///     .timeline()
+///     .assert_before(Side::Client.wrote("ping"), Side::Server.wrote("pong"))
+///     .assert_no_data_after(Side::Server.shutdown());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    events: Vec<RecordedEvent>,
+    started_at: SystemTime,
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+}
+
+impl Timeline {
+    pub(crate) fn new(
+        events: Vec<RecordedEvent>,
+        started_at: SystemTime,
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> Self {
+        Self {
+            events,
+            started_at,
+            client_addr,
+            server_addr,
+        }
+    }
+
+    /// Returns all the events in the timeline
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Returns the index of the first event matching given matcher (if any)
+    pub fn position(&self, matcher: &Matcher) -> Option<usize> {
+        self.find(matcher, 0)
+    }
+
+    /// Asserts that an event matching given matcher was recorded
+    #[track_caller]
+    pub fn assert_occurs(&self, matcher: Matcher) -> &Self {
+        if self.position(&matcher).is_none() {
+            self.fail(format_args!("expected {}", matcher));
+        }
+
+        self
+    }
+
+    /// Asserts that the first event matching `earlier` was recorded before the first event matching `later`
+    #[track_caller]
+    pub fn assert_before(&self, earlier: Matcher, later: Matcher) -> &Self {
+        match (self.position(&earlier), self.position(&later)) {
+            (Some(first), Some(second)) if first < second => {}
+            (Some(first), Some(second)) => self.fail(format_args!(
+                "expected {} (#{}) before {} (#{})",
+                earlier, first, later, second
+            )),
+            (None, _) => self.fail(format_args!("expected {} before {}", earlier, later)),
+            (_, None) => self.fail(format_args!("expected {} after {}", later, earlier)),
+        }
+
+        self
+    }
+
+    /// Asserts that events matching given matchers were recorded in the given order (each matcher is searched after
+    /// the event matched by the previous one, so other events may be interleaved)
+    #[track_caller]
+    pub fn assert_sequence(&self, matchers: impl IntoIterator<Item = Matcher>) -> &Self {
+        let mut from = 0;
+        let mut previous: Option<Matcher> = None;
+
+        for matcher in matchers {
+            match self.find(&matcher, from) {
+                Some(index) => from = index + 1,
+                None => match previous {
+                    Some(previous) => {
+                        self.fail(format_args!("expected {} after {}", matcher, previous))
+                    }
+                    None => self.fail(format_args!("expected {}", matcher)),
+                },
+            }
+
+            previous = Some(matcher);
+        }
+
+        self
+    }
+
+    /// Asserts that no data was written after the first event matching given matcher (which must have been recorded)
+    ///
+    /// Only data written by the side of the matched event is considered (e.g., the peer of a side which shut down may
+    /// still write), except after a reset, which covers both sides.
+    #[track_caller]
+    pub fn assert_no_data_after(&self, matcher: Matcher) -> &Self {
+        let index = match self.position(&matcher) {
+            Some(index) => index,
+            None => self.fail(format_args!("expected {}", matcher)),
+        };
+
+        let side = match &matcher.0 {
+            MatcherKind::Data(side, _) | MatcherKind::Shutdown(side) => Some(*side),
+            MatcherKind::Reset => None,
+        };
+
+        let data = self.events[index + 1..]
+            .iter()
+            .position(|event| match event {
+                RecordedEvent::Data { from, .. } => side.is_none() || side == Some(self.side(from)),
+                _ => false,
+            });
+
+        if let Some(offset) = data {
+            self.fail(format_args!(
+                "expected no data after {} (#{}), found data at #{}",
+                matcher,
+                index,
+                index + 1 + offset
+            ));
+        }
+
+        self
+    }
+
+    #[track_caller]
+    fn fail(&self, message: fmt::Arguments<'_>) -> ! {
+        panic!("timeline assertion failed: {}\n\n{}", message, self)
+    }
+
+    /// Returns the index of the first event at or after `from` matching given matcher
+    fn find(&self, matcher: &Matcher, from: usize) -> Option<usize> {
+        let mut events = self.events.iter().enumerate().skip(from);
+
+        match &matcher.0 {
+            MatcherKind::Data(side, pattern) => {
+                // Owner of every byte written by the side (to match patterns split across writes)
+                let mut bytes = Vec::new();
+                let mut owners = Vec::new();
+
+                for (index, event) in events {
+                    if let RecordedEvent::Data { from, bytes: data, .. } = event {
+                        if self.side(from) == *side {
+                            if pattern.is_empty() {
+                                return Some(index);
+                            }
+
+                            bytes.extend_from_slice(data);
+                            owners.extend(data.iter().map(|_| index));
+                        }
+                    }
+                }
+
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                bytes
+                    .windows(pattern.len())
+                    .position(|window| window == &pattern[..])
+                    .map(|start| owners[start + pattern.len() - 1])
+            }
+            MatcherKind::Shutdown(side) => events
+                .find(|(_, event)| {
+                    matches!(event, RecordedEvent::Shutdown { from, .. } if self.side(from) == *side)
+                })
+                .map(|(index, _)| index),
+            MatcherKind::Reset => events
+                .find(|(_, event)| matches!(event, RecordedEvent::Reset { .. }))
+                .map(|(index, _)| index),
+        }
+    }
+
+    fn side(&self, from: &SocketAddr) -> Side {
+        if *from == self.client_addr {
+            Side::Client
+        } else {
+            Side::Server
+        }
+    }
+}
+
+impl Display for Timeline {
+    /// Renders the events of both directions side by side (one row per event)
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let client = format!("client ({})", self.client_addr);
+        let server = format!("server ({})", self.server_addr);
+
+        writeln!(
+            f,
+            "{:>4} | {:>10} | {:<width$} | {}",
+            "#",
+            "time",
+            client,
+            server,
+            width = COLUMN_WIDTH
+        )?;
+        writeln!(
+            f,
+            "{:-<4}-+-{:-<10}-+-{:-<width$}-+-{:-<width$}",
+            "",
+            "",
+            "",
+            "",
+            width = COLUMN_WIDTH
+        )?;
+
+        for (index, event) in self.events.iter().enumerate() {
+            let (at, cell, side) = match event {
+                RecordedEvent::Data {
+                    from, bytes, at, ..
+                } => (at, truncate(escape(bytes)), Some(self.side(from))),
+                RecordedEvent::Shutdown { from, at, .. } => {
+                    (at, "<shutdown>".to_owned(), Some(self.side(from)))
+                }
+                RecordedEvent::Reset { at, .. } => (at, "<reset>".to_owned(), None),
+            };
+
+            let elapsed = at.duration_since(self.started_at).unwrap_or_default();
+
+            let (client, server) = match side {
+                Some(Side::Client) => (cell.as_str(), ""),
+                Some(Side::Server) => ("", cell.as_str()),
+                None => (cell.as_str(), cell.as_str()),
+            };
+
+            let row = format!(
+                "{:>4} | {:>10} | {:<width$} | {}",
+                index,
+                format!("{:.3?}", elapsed),
+                client,
+                server,
+                width = COLUMN_WIDTH
+            );
+
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escapes given bytes as a quoted (mostly ASCII) string
+fn escape(bytes: &[u8]) -> String {
+    let escaped: Vec<u8> = bytes
+        .iter()
+        .flat_map(|byte| ascii::escape_default(*byte))
+        .collect();
+
+    format!("\"{}\"", String::from_utf8_lossy(&escaped))
+}
+
+/// Truncates given cell to the width of a column
+fn truncate(mut cell: String) -> String {
+    if cell.len() > COLUMN_WIDTH {
+        cell.truncate(COLUMN_WIDTH - 3);
+        cell.push_str("...");
+    }
+
+    cell
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+
+    use std::{
+        io::{Read, Write},
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use crate::sync::MockStream;
+
+    #[test]
+    fn check_timeline_assertions() {
+        let (client, mut server) = MockStream::pair();
+        let recording = client.record();
+        let (_client_read_half, mut client_write_half) = client.split();
+
+        client_write_half.write_all(b"GET ").unwrap();
+        client_write_half.write_all(b"/\n").unwrap();
+
+        let mut buf = [0; 6];
+        server.read_exact(&mut buf).unwrap();
+        server.write_all(b"200 OK\n").unwrap();
+        drop(client_write_half);
+        server.write_all(b"bye\n").unwrap();
+        drop(server);
+
+        let timeline = recording.timeline();
+
+        timeline
+            .assert_occurs(Side::Client.shutdown())
+            .assert_before(Side::Client.wrote("GET /"), Side::Server.wrote("200"))
+            .assert_sequence(vec![
+                Side::Client.wrote(""),
+                Side::Server.wrote("OK"),
+                Side::Client.shutdown(),
+                Side::Server.wrote("bye"),
+            ])
+            .assert_no_data_after(Side::Server.shutdown());
+
+        assert_eq!(Some(1), timeline.position(&Side::Client.wrote("T /")));
+        assert_eq!(None, timeline.position(&Matcher::reset()));
+
+        // Server keeps writing after the client shut down its write side
+        timeline.assert_no_data_after(Side::Client.shutdown());
+
+        let error = panic::catch_unwind(AssertUnwindSafe(|| {
+            timeline.assert_no_data_after(Side::Server.wrote("OK"));
+        }))
+        .unwrap_err();
+
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with(
+            "timeline assertion failed: expected no data after server wrote \"OK\" (#2), found data at #4"
+        ));
+        assert!(message.contains("\"200 OK\\n\""));
+        assert!(message.contains("<shutdown>"));
+
+        let (client, mut server) = MockStream::pair();
+        let recording = client.record();
+        server.write_all(b"hello").unwrap();
+
+        let timeline = recording.timeline();
+        assert_eq!(Some(0), timeline.position(&Side::Server.wrote("")));
+        assert_eq!(None, timeline.position(&Side::Client.wrote("")));
+    }
+}
@@ -0,0 +1,237 @@
+//! Pre-generated test certificate authority and `rustls` configurations for wrapping mock IO streams in TLS
+use std::{convert::TryFrom, sync::Arc};
+
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    server::WebPkiClientVerifier,
+    version::{TLS12, TLS13},
+    ClientConfig, RootCertStore, ServerConfig, SupportedProtocolVersion,
+};
+
+/// Certificate of the test certificate authority
+const CA_CERTIFICATE: &str = include_str!("tls/ca.pem");
+/// Certificate for `localhost` (and `127.0.0.1`) issued by the test certificate authority
+const CERTIFICATE: &str = include_str!("tls/localhost.pem");
+/// Certificate for `localhost` issued by the test certificate authority which expired in 2001
+const EXPIRED_CERTIFICATE: &str = include_str!("tls/expired.pem");
+/// Self-signed certificate for `localhost` (not issued by the test certificate authority)
+const UNTRUSTED_CERTIFICATE: &str = include_str!("tls/untrusted.pem");
+/// Private key of all the certificates for `localhost`
+const PRIVATE_KEY: &str = include_str!("tls/localhost.key");
+
+/// Name of the server used by clients when the server name is not faulted
+const SERVER_NAME: &str = "localhost";
+/// Name of the server used by clients with `TlsFault::WrongServerName`
+const WRONG_SERVER_NAME: &str = "wrong.invalid";
+
+/// Pre-generated test certificate authority along with a certificate (and its private key) issued by it
+///
+/// The certificate is valid for `localhost` and `127.0.0.1` (until 2124) and can be used by both servers and
+/// clients. None of this material is secret, so it must never be used outside tests.
+#[derive(Debug, Clone, Copy)]
+pub struct TestCa;
+
+impl TestCa {
+    /// Returns the certificate of the test certificate authority
+    pub fn ca_certificate() -> CertificateDer<'static> {
+        parse_certificate(CA_CERTIFICATE)
+    }
+
+    /// Returns the certificate of the test certificate authority in PEM format
+    pub fn ca_certificate_pem() -> &'static str {
+        CA_CERTIFICATE
+    }
+
+    /// Returns the certificate issued by the test certificate authority
+    pub fn certificate() -> CertificateDer<'static> {
+        parse_certificate(CERTIFICATE)
+    }
+
+    /// Returns the certificate issued by the test certificate authority in PEM format
+    pub fn certificate_pem() -> &'static str {
+        CERTIFICATE
+    }
+
+    /// Returns the private key of the certificate issued by the test certificate authority
+    pub fn private_key() -> PrivateKeyDer<'static> {
+        PrivateKeyDer::from_pem_slice(PRIVATE_KEY.as_bytes()).expect("invalid test private key")
+    }
+
+    /// Returns the private key of the certificate issued by the test certificate authority in PEM (PKCS #8) format
+    pub fn private_key_pem() -> &'static str {
+        PRIVATE_KEY
+    }
+
+    /// Returns a root certificate store trusting only the test certificate authority
+    pub fn root_store() -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(Self::ca_certificate())
+            .expect("invalid test CA certificate");
+        roots
+    }
+}
+
+/// Fault injected into the TLS handshake between a pair of mock IO streams
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TlsFault {
+    /// Server presents a certificate which has expired
+    ExpiredCertificate,
+    /// Server presents a self-signed certificate which is not issued by the test certificate authority
+    UntrustedCertificate,
+    /// Client expects a server name which is not in the certificate of the server
+    WrongServerName,
+    /// Client only supports TLS 1.3 while server only supports TLS 1.2
+    ProtocolMismatch,
+    /// Server requires a client certificate but client does not present one
+    MissingClientCertificate,
+}
+
+/// Options for wrapping a pair of mock IO streams in TLS
+///
+/// By default, client trusts only the test certificate authority, server presents the certificate issued by it,
+/// clients do not authenticate and no ALPN protocols are offered. Configurations built from the options can also be
+/// used with any other `rustls` based stack (e.g., `tokio-rustls`).
+///
+/// ```rust
+/// use mock_io::sync::{TlsFault, TlsOptions};
+///
+/// let options = TlsOptions::new()
+///     .client_alpn(["h2", "http/1.1"])
+///     .server_alpn(["http/1.1"])
+///     .fault(TlsFault::ExpiredCertificate);
+///
+/// let client_config = options.client_config();
+/// assert_eq!(vec![b"h2".to_vec(), b"http/1.1".to_vec()], client_config.alpn_protocols);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsOptions {
+    client_alpn: Vec<Vec<u8>>,
+    server_alpn: Vec<Vec<u8>>,
+    client_auth: bool,
+    fault: Option<TlsFault>,
+}
+
+impl TlsOptions {
+    /// Creates default TLS options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ALPN protocols offered by the client (in the order of preference)
+    pub fn client_alpn<P: Into<Vec<u8>>>(mut self, protocols: impl IntoIterator<Item = P>) -> Self {
+        self.client_alpn = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the ALPN protocols supported by the server. Server picks the first protocol offered by the client which it
+    /// supports and the handshake fails if it supports none of them.
+    pub fn server_alpn<P: Into<Vec<u8>>>(mut self, protocols: impl IntoIterator<Item = P>) -> Self {
+        self.server_alpn = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Makes both the client and the server support only given ALPN protocol (so that it is always negotiated)
+    pub fn alpn(self, protocol: impl Into<Vec<u8>>) -> Self {
+        let protocol = protocol.into();
+
+        self.client_alpn([protocol.clone()]).server_alpn([protocol])
+    }
+
+    /// Sets whether the server requires clients to present a certificate issued by the test certificate authority
+    /// (clients present the certificate returned by `TestCa::certificate`)
+    pub fn client_auth(mut self, client_auth: bool) -> Self {
+        self.client_auth = client_auth;
+        self
+    }
+
+    /// Injects given fault into the handshake
+    pub fn fault(mut self, fault: TlsFault) -> Self {
+        self.fault = Some(fault);
+        self
+    }
+
+    /// Returns the name of the server expected by the client
+    pub fn server_name(&self) -> ServerName<'static> {
+        let name = match self.fault {
+            Some(TlsFault::WrongServerName) => WRONG_SERVER_NAME,
+            _ => SERVER_NAME,
+        };
+
+        ServerName::try_from(name).expect("invalid test server name")
+    }
+
+    /// Builds the configuration of the client
+    pub fn client_config(&self) -> Arc<ClientConfig> {
+        let versions: &[&SupportedProtocolVersion] = match self.fault {
+            Some(TlsFault::ProtocolMismatch) => &[&TLS13],
+            _ => &[&TLS13, &TLS12],
+        };
+
+        let builder = ClientConfig::builder_with_provider(provider())
+            .with_protocol_versions(versions)
+            .expect("unsupported protocol versions")
+            .with_root_certificates(TestCa::root_store());
+
+        let mut config =
+            if self.client_auth && self.fault != Some(TlsFault::MissingClientCertificate) {
+                builder
+                    .with_client_auth_cert(vec![TestCa::certificate()], TestCa::private_key())
+                    .expect("invalid test client certificate")
+            } else {
+                builder.with_no_client_auth()
+            };
+
+        config.alpn_protocols = self.client_alpn.clone();
+        Arc::new(config)
+    }
+
+    /// Builds the configuration of the server
+    pub fn server_config(&self) -> Arc<ServerConfig> {
+        let versions: &[&SupportedProtocolVersion] = match self.fault {
+            Some(TlsFault::ProtocolMismatch) => &[&TLS12],
+            _ => &[&TLS13, &TLS12],
+        };
+
+        let certificate = match self.fault {
+            Some(TlsFault::ExpiredCertificate) => parse_certificate(EXPIRED_CERTIFICATE),
+            Some(TlsFault::UntrustedCertificate) => parse_certificate(UNTRUSTED_CERTIFICATE),
+            _ => TestCa::certificate(),
+        };
+
+        let builder = ServerConfig::builder_with_provider(provider())
+            .with_protocol_versions(versions)
+            .expect("unsupported protocol versions");
+
+        let builder = if self.client_auth || self.fault == Some(TlsFault::MissingClientCertificate)
+        {
+            let verifier = WebPkiClientVerifier::builder_with_provider(
+                Arc::new(TestCa::root_store()),
+                provider(),
+            )
+            .build()
+            .expect("invalid test client verifier");
+
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        let mut config = builder
+            .with_single_cert(vec![certificate], TestCa::private_key())
+            .expect("invalid test server certificate");
+
+        config.alpn_protocols = self.server_alpn.clone();
+        Arc::new(config)
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn parse_certificate(pem: &str) -> CertificateDer<'static> {
+    CertificateDer::from_pem_slice(pem.as_bytes()).expect("invalid test certificate")
+}
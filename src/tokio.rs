@@ -1,3 +1,31 @@
 //! Mock IO stream and listener in tokio context
+pub use crate::controller::{Controller, Direction};
+pub use crate::datagram::DatagramFaults;
+pub use crate::flush::FlushMode;
+pub use crate::intercept::{Action, Interceptor};
+pub use crate::lifecycle::ConnectionEvent;
+pub use crate::link::Link;
+#[cfg(feature = "tokio-util")]
+pub use crate::mock_framed::tokio::*;
 pub use crate::mock_listener::tokio::*;
+pub use crate::mock_peer::tokio::*;
+pub use crate::mock_proxy::tokio::*;
+pub use crate::mock_seqpacket::tokio::*;
+pub use crate::mock_server::tokio::*;
 pub use crate::mock_stream::tokio::*;
+pub use crate::mock_udp_socket::tokio::*;
+pub use crate::mock_unix_listener::tokio::*;
+pub use crate::mock_unix_stream::tokio::*;
+pub use crate::peer::Peer;
+pub use crate::recording::{RecordedEvent, Recording};
+pub use crate::script::{ChunkPolicy, Fault, FaultPlan, Script};
+pub use crate::server::{Codec, LineCodec, Reply};
+pub use crate::simulation::Simulation;
+pub use crate::socket_options::SocketOptions;
+pub use crate::stats::Stats;
+#[cfg(not(loom))]
+pub use crate::tcp_bridge::tokio::*;
+pub use crate::timeline::{Matcher, Side, Timeline};
+#[cfg(feature = "rustls")]
+pub use crate::tls::{TestCa, TlsFault, TlsOptions};
+pub use crate::unix::{Ancillary, Credentials, UnixSocketAddr};
@@ -0,0 +1,176 @@
+//! Addresses and ancillary data of mock unix domain sockets
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError},
+};
+
+use crate::primitives::{Mutex, MutexGuard};
+
+/// Address of a mock unix domain socket
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UnixSocketAddr {
+    path: Option<PathBuf>,
+}
+
+impl UnixSocketAddr {
+    pub(crate) fn unnamed() -> Self {
+        Default::default()
+    }
+
+    pub(crate) fn from_path(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: Some(path.as_ref().to_path_buf()),
+        }
+    }
+
+    /// Returns `true` if the address is unnamed
+    pub fn is_unnamed(&self) -> bool {
+        self.path.is_none()
+    }
+
+    /// Returns the contents of this address if it is a pathname address
+    pub fn as_pathname(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// Credentials of a process (emulates `SCM_CREDENTIALS` ancillary message)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Credentials {
+    /// Process ID
+    pub pid: u32,
+    /// User ID
+    pub uid: u32,
+    /// Group ID
+    pub gid: u32,
+}
+
+/// Ancillary data sent along with bytes over a mock unix domain stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Ancillary {
+    /// File descriptors (emulates `SCM_RIGHTS` ancillary message). No real file descriptors are transferred.
+    Rights(Vec<i32>),
+    /// Credentials of the sending process (emulates `SCM_CREDENTIALS` ancillary message)
+    Credentials(Credentials),
+}
+
+/// Queue of ancillary data in one direction of a mock unix domain stream. Every entry is attached to the offset of
+/// the first byte of the write it was sent with.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AncillaryQueue {
+    inner: Arc<Mutex<VecDeque<(u64, Ancillary)>>>,
+}
+
+impl AncillaryQueue {
+    /// Attaches ancillary data to the byte at given offset
+    pub(crate) fn push(&self, offset: u64, ancillary: Vec<Ancillary>) {
+        self.lock()
+            .extend(ancillary.into_iter().map(|ancillary| (offset, ancillary)));
+    }
+
+    /// Removes the ancillary data attached to the byte at given offset (or after it)
+    pub(crate) fn retract(&self, offset: u64) {
+        let mut queue = self.lock();
+
+        while matches!(queue.back(), Some((last, _)) if *last >= offset) {
+            queue.pop_back();
+        }
+    }
+
+    /// Returns the ancillary data attached to the bytes in `start..end`. Ancillary data attached to bytes before
+    /// `start` (i.e., bytes read without receiving ancillary data) is discarded.
+    pub(crate) fn take(&self, start: u64, end: u64) -> Vec<Ancillary> {
+        let mut queue = self.lock();
+        let mut ancillary = Vec::new();
+
+        while let Some((offset, _)) = queue.front() {
+            if *offset >= end {
+                break;
+            }
+
+            if let Some((offset, data)) = queue.pop_front() {
+                if offset >= start {
+                    ancillary.push(data);
+                }
+            }
+        }
+
+        ancillary
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<(u64, Ancillary)>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Ancillary state of one end of a mock unix domain stream
+#[derive(Debug)]
+pub(crate) struct AncillaryState {
+    outgoing: AncillaryQueue,
+    incoming: AncillaryQueue,
+    written: u64,
+    read: u64,
+}
+
+impl AncillaryState {
+    pub(crate) fn pair() -> (Self, Self) {
+        let queue_1 = AncillaryQueue::default();
+        let queue_2 = AncillaryQueue::default();
+
+        (
+            Self {
+                outgoing: queue_1.clone(),
+                incoming: queue_2.clone(),
+                written: 0,
+                read: 0,
+            },
+            Self {
+                outgoing: queue_2,
+                incoming: queue_1,
+                written: 0,
+                read: 0,
+            },
+        )
+    }
+
+    /// Attaches ancillary data to the next byte written
+    pub(crate) fn attach(&self, ancillary: Vec<Ancillary>) {
+        self.outgoing.push(self.written, ancillary);
+    }
+
+    /// Removes the ancillary data attached to the next byte written (i.e., when the write it was sent with fails)
+    pub(crate) fn detach(&self) {
+        self.outgoing.retract(self.written);
+    }
+
+    pub(crate) fn record_write(&mut self, len: usize) {
+        self.written += len as u64;
+    }
+
+    /// Records a read of `len` bytes and returns the ancillary data attached to them
+    pub(crate) fn record_read(&mut self, len: usize) -> Vec<Ancillary> {
+        let start = self.read;
+        self.read += len as u64;
+        self.incoming.take(start, self.read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_detached_ancillary_data() {
+        let (mut client, mut server) = AncillaryState::pair();
+
+        client.attach(vec![Ancillary::Rights(vec![1])]);
+        client.detach();
+
+        client.attach(vec![Ancillary::Rights(vec![2])]);
+        client.record_write(1);
+
+        assert_eq!(vec![Ancillary::Rights(vec![2])], server.record_read(1));
+    }
+}